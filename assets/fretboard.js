@@ -5,6 +5,11 @@ const DEFAULT_OPTS = {
   endFret: 4,
   stringNames: "EBGDAE".split(""),
   dots: [],
+  // {fret, strings}, strings is null for a full capo
+  capo: null,
+  // where each string's own nut is, string 1 first, for short strings like a
+  // banjo's 5th string
+  nutFrets: [],
   dotColor: "white",
   hoverDotColor: "white",
   showFretNums: true,
@@ -32,6 +37,7 @@ export class Fretboard {
 
     this.addStrings();
     this.addFrets();
+    this.addCapo();
     this.addDots();
 
     if (this.opts.drawDotOnHover) {
@@ -48,7 +54,9 @@ export class Fretboard {
   addStrings() {
     for (let i = 0; i < this.numStrings; i++) {
       const x = (i * this.stringMargin) + this.xMargin;
-      const y1 = this.yMargin;
+      const nutFret = this.opts.nutFrets[this.numStrings - i - 1] ?? 0;
+      const nutRow = Math.min(Math.max(nutFret - this.topFret, 0), this.numFrets);
+      const y1 = (nutRow * this.fretHeight) + this.yMargin;
       const y2 = this.yMargin + this.neckHeight;
       const line = makeLine(x, y1, x, y2);
       this.svg.appendChild(line);
@@ -65,6 +73,26 @@ export class Fretboard {
     }
   }
 
+  addCapo() {
+    const capo = this.opts.capo;
+    if (!capo) return;
+
+    const coords = [];
+    for (let string = 1; string <= this.numStrings; string++) {
+      if (!capo.strings || capo.strings.includes(string)) {
+        coords.push(this.fretCoord(string, capo.fret));
+      }
+    }
+    if (coords.length === 0) return;
+
+    const xs = coords.map(c => c.x);
+    const y = coords[0].y;
+    const line = makeLine(Math.min(...xs), y, Math.max(...xs), y);
+    line.setAttribute('stroke-width', (this.dotRadius * 1.5).toString());
+    line.setAttribute('stroke-linecap', 'round');
+    this.svg.appendChild(line);
+  }
+
   addDots() {
     for (const dot of this.opts.dots) {
       const elem = this.makeDot(dot.string, dot.fret, dot.color);
@@ -113,11 +141,20 @@ export class Fretboard {
     this.svg.appendChild(dot);
   }
 
+  /**
+   * The fret drawn just above the top space of the neck: the nut, or the fret
+   * below startFret when the board starts further up.
+   */
+  get topFret() {
+    return this.opts.startFret === 0 ? 0 : this.opts.startFret - 1;
+  }
+
   fretCoord(string, fret) {
     const stringOffset = Math.abs(string - this.numStrings);
+    const row = fret - this.topFret;
 
     const x = (stringOffset * this.stringMargin) + this.xMargin;
-    let y = ((fret * this.fretHeight) - (this.fretHeight / 2)) + this.yMargin;
+    let y = ((row * this.fretHeight) - (this.fretHeight / 2)) + this.yMargin;
 
     // place open string dots closer to the top of the fretboard
     if (fret === 0) {
@@ -139,8 +176,10 @@ export class Fretboard {
       string = this.numStrings;
     }
 
-    let fret = Math.round(y / this.fretHeight);
-    if (fret > this.opts.endFret) {
+    let fret = Math.round(y / this.fretHeight) + this.topFret;
+    if (fret < this.opts.startFret) {
+      fret = this.opts.startFret;
+    } else if (fret > this.opts.endFret) {
       fret = this.opts.endFret;
     }
 
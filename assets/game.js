@@ -27,6 +27,8 @@ if (TOKEN) {
   }
   socket.onmessage = event => {
     console.log("msg recv:", event);
+    // the server runs the game's clock, the page just shows where it's at
+    if (/^\{"(RoundStarted|RoundOver|GameOver|RosterChanged)"/.test(event.data)) location.reload();
   }
   socket.onclose = event => {
     console.log("ws closed:", event);
@@ -39,10 +41,33 @@ if (TOKEN) {
 }
 
 let noteToDraw;
+let stringNames;
+let keySignature;
+let accidental;
+let clef;
+let clefAnnotation;
+let startFret;
+let endFret;
+let capo;
+let nutFrets;
 
 const noteData = document.querySelector("#note-data");
 if (noteData) {
   noteToDraw = noteData.dataset.note;
+  stringNames = noteData.dataset.stringNames?.split(" ");
+  clef = noteData.dataset.clef || undefined;
+  clefAnnotation = noteData.dataset.clefAnnotation;
+  startFret = parseInt(noteData.dataset.startFret);
+  endFret = parseInt(noteData.dataset.endFret);
+  nutFrets = noteData.dataset.nutFrets?.split(" ").map(f => parseInt(f));
+  const capoFret = parseInt(noteData.dataset.capoFret);
+  if (capoFret) {
+    const strings = noteData.dataset.capoStrings?.split(" ").filter(s => s).map(s => parseInt(s));
+    capo = {fret: capoFret, strings: strings?.length ? strings : null};
+  }
+  keySignature = noteData.dataset.keySignature;
+  // with a key signature, the server decides which accidental to draw
+  if (keySignature) accidental = noteData.dataset.accidental;
 }
 
 const STAFF_WIDTH = 200;
@@ -50,17 +75,23 @@ const STAFF_HEIGHT = 130;
 
 const staffContainer = document.querySelector("#staff-container");
 if (staffContainer) {
-  const staff = new Staff(staffContainer, STAFF_WIDTH, STAFF_HEIGHT, noteToDraw);
+  const staff = new Staff(staffContainer, STAFF_WIDTH, STAFF_HEIGHT, noteToDraw, keySignature, accidental, clef, clefAnnotation);
   staffContainer.onclick = () => staff.clear();
 }
 
 function onFbClick(coord) {
-  console.log("clicked", coord);
+  socket.send(JSON.stringify({FretGuess: {string: coord.string, fret: coord.fret}}));
 }
 
 const fbContainer = document.querySelector("#fretboard-container");
 if (fbContainer) {
-  new Fretboard(fbContainer, {drawDotOnHover: true, onClick: onFbClick});
+  const fbOpts = {drawDotOnHover: true, onClick: onFbClick};
+  if (stringNames) fbOpts.stringNames = stringNames;
+  if (!isNaN(startFret)) fbOpts.startFret = startFret;
+  if (!isNaN(endFret)) fbOpts.endFret = endFret;
+  if (capo) fbOpts.capo = capo;
+  if (nutFrets) fbOpts.nutFrets = nutFrets;
+  new Fretboard(fbContainer, fbOpts);
 }
 
 const startGameBtn = document.querySelector("#start-game-btn");
@@ -70,3 +101,39 @@ if (startGameBtn) {
     socket.send(JSON.stringify(msg));
   };
 }
+
+const joinGameBtn = document.querySelector("#join-game-btn");
+if (joinGameBtn) {
+  joinGameBtn.onclick = () => socket.send(JSON.stringify("JoinGame"));
+}
+
+const leaveGameBtn = document.querySelector("#leave-game-btn");
+if (leaveGameBtn) {
+  leaveGameBtn.onclick = () => socket.send(JSON.stringify("LeaveGame"));
+}
+
+const NOTE_ON = 0x90;
+
+/**
+ * Send note-ons from any connected MIDI instrument as guesses.
+ * Channels are sent 1-based. Guitar controllers in MPE mode keep channel 1
+ * for the whole instrument and play string 1 on channel 2, string 2 on
+ * channel 3, and so on; the server works out the string.
+ */
+function onMidiMessage(event) {
+  const [status, note, velocity] = event.data;
+  if ((status & 0xf0) !== NOTE_ON || velocity === 0) return;
+
+  const channel = (status & 0x0f) + 1;
+  socket.send(JSON.stringify({MidiGuess: {note, channel}}));
+}
+
+if (navigator.requestMIDIAccess) {
+  navigator.requestMIDIAccess()
+    .then(access => {
+      for (const input of access.inputs.values()) {
+        input.onmidimessage = onMidiMessage;
+      }
+    })
+    .catch(e => console.error("midi unavailable:", e));
+}
@@ -13,24 +13,35 @@ function getAccidental(noteName) {
 }
 
 export class Staff {
-  constructor(parentEl, width, height, noteName) {
+  /**
+   * keySignature is a VexFlow key spec like "Bb" or "F#m". When accidental is
+   * given it overrides the accidental taken from noteName, "" draws none.
+   * noteName is the written note, clefAnnotation is e.g. "8vb" for guitar.
+   */
+  constructor(parentEl, width, height, noteName, keySignature, accidental, clef = "treble", clefAnnotation) {
     this.renderer = new VF.Renderer(parentEl, VF.Renderer.Backends.SVG);
     this.renderer.resize(width, height);
     this.context = this.renderer.getContext();
 
     const stave = new VF.Stave(0, 0, width-1)
       .setContext(this.context)
-      .addClef("treble")
-      .draw();
+      .addClef(clef, "default", clefAnnotation || undefined);
+
+    if (keySignature) {
+      stave.addKeySignature(keySignature);
+    }
+
+    stave.draw();
 
     if (noteName) {
       const note = new VF.StaveNote({
         keys: [noteName],
         duration: "w",
+        clef,
         align_center: true,
       }).setStave(stave);
 
-      const acc = getAccidental(noteName);
+      const acc = accidental ?? getAccidental(noteName);
       if (acc) {
         note.addModifier(new VF.Accidental(acc));
       }
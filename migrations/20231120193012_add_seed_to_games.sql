@@ -0,0 +1 @@
+ALTER TABLE games ADD COLUMN seed INTEGER NOT NULL DEFAULT 0;
@@ -1,21 +1,40 @@
+use crate::game::GameId;
 use sqlx::{Pool, Sqlite};
 use std::collections::HashMap;
-use std::sync::Mutex;
-use tokio::sync::broadcast;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{self, broadcast, Notify};
 
 pub struct AppState {
     pub pool: Pool<Sqlite>,
-    pub rooms: Mutex<HashMap<String, Room>>,
+    /// One room for each game with someone connected or a clock running.
+    pub rooms: Mutex<HashMap<GameId, Room>>,
+    /// Rendered note audio, keyed by midi number.
+    pub note_wavs: Mutex<HashMap<i32, Vec<u8>>>,
 }
 
+impl AppState {
+    /// The game's room, opened if it isn't yet.
+    pub fn room(&self, game_id: GameId) -> Room {
+        let mut rooms = self.rooms.lock().unwrap();
+        rooms.entry(game_id).or_insert_with(Room::new).clone()
+    }
+}
+
+#[derive(Clone)]
 pub struct Room {
     pub tx: broadcast::Sender<String>,
+    /// Wakes the clock of the room's game, see `game::clock`.
+    pub clock: Arc<Notify>,
+    /// Held while the game is changed, see `game::db::change_game`.
+    pub lock: Arc<sync::Mutex<()>>,
 }
 
 impl Room {
     pub(crate) fn new() -> Self {
         Self {
             tx: broadcast::channel(16).0,
+            clock: Arc::new(Notify::new()),
+            lock: Arc::new(sync::Mutex::new(())),
         }
     }
 }
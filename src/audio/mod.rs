@@ -0,0 +1,205 @@
+pub mod pitch;
+pub mod synth;
+
+use std::{error, fmt};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+const PCM_FORMAT: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Encodes mono samples in [-1, 1] as a 16-bit PCM WAV file.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let block_align = BITS_PER_SAMPLE / 8;
+    let data_len = (samples.len() * block_align as usize) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&PCM_FORMAT.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let pcm = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        bytes.extend_from_slice(&pcm.to_le_bytes());
+    }
+
+    bytes
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeWavError {
+    NotWav,
+    UnsupportedFormat,
+    MissingChunk,
+}
+
+impl fmt::Display for DecodeWavError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotWav => write!(f, "not a RIFF WAVE file"),
+            Self::UnsupportedFormat => write!(f, "only 16-bit PCM is supported"),
+            Self::MissingChunk => write!(f, "missing fmt or data chunk"),
+        }
+    }
+}
+
+impl error::Error for DecodeWavError {}
+
+/// Decodes a 16-bit PCM WAV file into its sample rate and samples in [-1, 1].
+/// Multi-channel files are mixed down to mono.
+pub fn decode_wav(bytes: &[u8]) -> Result<(u32, Vec<f32>), DecodeWavError> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(DecodeWavError::NotWav);
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut rest = &bytes[12..];
+
+    while rest.len() >= 8 {
+        let id = &rest[..4];
+        let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let body = rest.get(8..8 + len).ok_or(DecodeWavError::MissingChunk)?;
+
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let read_u16 = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                format = Some((read_u16(0), read_u16(2), sample_rate, read_u16(14)));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // chunks are padded to an even length
+        let next = (8 + len + len % 2).min(rest.len());
+        rest = &rest[next..];
+    }
+
+    let (audio_format, channels, sample_rate, bits) = format.ok_or(DecodeWavError::MissingChunk)?;
+    let data = data.ok_or(DecodeWavError::MissingChunk)?;
+
+    if audio_format != PCM_FORMAT || bits != BITS_PER_SAMPLE || channels == 0 {
+        return Err(DecodeWavError::UnsupportedFormat);
+    }
+
+    let samples = data
+        .chunks_exact(2 * channels as usize)
+        .map(|frame| {
+            let sum: f32 = frame
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+                .sum();
+            sum / channels as f32
+        })
+        .collect();
+
+    Ok((sample_rate, samples))
+}
+
+/// Splits a binary PCM frame sent by a client into its sample rate and
+/// samples. A frame is the sample rate as a little-endian u32, followed by
+/// little-endian f32 mono samples.
+pub fn decode_pcm_frame(bytes: &[u8]) -> Option<(u32, Vec<f32>)> {
+    let (header, body) = bytes.split_at_checked(4)?;
+    let sample_rate = u32::from_le_bytes(header.try_into().ok()?);
+    if sample_rate == 0 || body.len() % 4 != 0 {
+        return None;
+    }
+
+    let samples = body
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    Some((sample_rate, samples))
+}
+
+/// Brings `samples` down to at most `max_rate` by averaging each block of
+/// the smallest whole number of samples that gets there. Returns the new
+/// sample rate along with the samples, which are left alone if they're
+/// already slow enough.
+pub fn downsample(sample_rate: u32, samples: Vec<f32>, max_rate: u32) -> (u32, Vec<f32>) {
+    let factor = sample_rate.div_ceil(max_rate);
+    if factor <= 1 {
+        return (sample_rate, samples);
+    }
+
+    let samples = samples
+        .chunks(factor as usize)
+        .map(|block| block.iter().sum::<f32>() / block.len() as f32)
+        .collect();
+
+    (sample_rate / factor, samples)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::*;
+
+    #[test]
+    fn test_wav_round_trip() {
+        let samples: Vec<f32> = (0..1000).map(|i| (i as f32 / 50.0).sin() * 0.5).collect();
+        let bytes = encode_wav(&samples, SAMPLE_RATE);
+        assert_eq!(bytes.len(), 44 + 2 * samples.len());
+
+        let (sample_rate, decoded) = decode_wav(&bytes).unwrap();
+        assert_eq!(sample_rate, SAMPLE_RATE);
+        assert_eq!(decoded.len(), samples.len());
+        assert!(samples
+            .iter()
+            .zip(&decoded)
+            .all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_decode_pcm_frame() {
+        let mut bytes = 48_000u32.to_le_bytes().to_vec();
+        for s in [0.5f32, -0.25, 1.0] {
+            bytes.extend_from_slice(&s.to_le_bytes());
+        }
+
+        assert_eq!(
+            decode_pcm_frame(&bytes),
+            Some((48_000, vec![0.5, -0.25, 1.0]))
+        );
+        assert_eq!(decode_pcm_frame(&bytes[..5]), None);
+        assert_eq!(decode_pcm_frame(&[0, 0, 0, 0]), None);
+        assert_eq!(decode_pcm_frame(&[1]), None);
+    }
+
+    #[test]
+    fn test_downsample() {
+        let samples = vec![0.5, 0.25, -1.0, 0.0, 1.0];
+        assert_eq!(
+            downsample(48_000, samples.clone(), 48_000),
+            (48_000, samples.clone())
+        );
+        assert_eq!(
+            downsample(96_000, samples.clone(), 48_000),
+            (48_000, vec![0.375, -0.5, 1.0])
+        );
+        assert_eq!(downsample(192_000, samples, 48_000).0, 48_000);
+        assert_eq!(downsample(88_200, vec![], 48_000), (44_100, vec![]));
+    }
+
+    #[test]
+    fn test_decode_wav_errors() {
+        assert_eq!(decode_wav(b"nope").unwrap_err(), DecodeWavError::NotWav);
+        assert_eq!(
+            decode_wav(b"RIFF\0\0\0\0WAVE").unwrap_err(),
+            DecodeWavError::MissingChunk
+        );
+    }
+}
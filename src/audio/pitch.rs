@@ -0,0 +1,249 @@
+use crate::theory::Note;
+
+/// Lowest pitch detected, a little under a bass's low E (41 Hz).
+const MIN_HZ: f64 = 38.0;
+
+/// Highest pitch detected, around the 24th fret of a guitar's high E.
+const MAX_HZ: f64 = 1400.0;
+
+/// YIN's absolute threshold on the normalized difference function. Lower is
+/// stricter.
+const YIN_THRESHOLD: f64 = 0.15;
+
+/// Windows quieter than this RMS level are treated as silence.
+const SILENCE_RMS: f64 = 0.01;
+
+/// Detects the fundamental frequency at the start of `samples` with the YIN
+/// algorithm (de Cheveigné and Kawahara, 2002). Returns `None` for silence,
+/// unpitched noise, or buffers too short to hold two periods of the lowest
+/// pitch.
+pub fn detect_pitch(samples: &[f32], sample_rate: u32) -> Option<f64> {
+    let sr = sample_rate as f64;
+    let min_tau = (sr / MAX_HZ).floor().max(2.0) as usize;
+    let max_tau = (sr / MIN_HZ).ceil() as usize;
+    // compare windows as long as the longest period, so that only the start
+    // of a long buffer is analysed
+    let width = max_tau;
+    if samples.len() < width + max_tau + 2 {
+        return None;
+    }
+
+    let window = &samples[..width + max_tau + 2];
+    let rms = (window.iter().map(|&s| (s * s) as f64).sum::<f64>() / window.len() as f64).sqrt();
+    if rms < SILENCE_RMS {
+        return None;
+    }
+
+    // difference function
+    let diff: Vec<f64> = (0..=max_tau + 1)
+        .map(|tau| {
+            (0..width)
+                .map(|i| {
+                    let d = (samples[i] - samples[i + tau]) as f64;
+                    d * d
+                })
+                .sum()
+        })
+        .collect();
+
+    // cumulative mean normalized difference
+    let mut cmnd = vec![1.0; diff.len()];
+    let mut running_sum = 0.0;
+    for tau in 1..diff.len() {
+        running_sum += diff[tau];
+        cmnd[tau] = if running_sum > 0.0 {
+            diff[tau] * tau as f64 / running_sum
+        } else {
+            1.0
+        };
+    }
+
+    let mut tau = (min_tau..=max_tau).find(|&tau| cmnd[tau] < YIN_THRESHOLD)?;
+    while tau < max_tau && cmnd[tau + 1] < cmnd[tau] {
+        tau += 1;
+    }
+
+    // parabolic interpolation around the minimum
+    let (a, b, c) = (cmnd[tau - 1], cmnd[tau], cmnd[tau + 1]);
+    let denom = a - 2.0 * b + c;
+    let shift = if denom.abs() > f64::EPSILON {
+        0.5 * (a - c) / denom
+    } else {
+        0.0
+    };
+
+    Some(sr / (tau as f64 + shift))
+}
+
+/// How many consecutive windows must agree on a note before it counts.
+const STABLE_WINDOWS: usize = 3;
+
+/// Turns a stream of audio into played notes. Audio is analysed in
+/// overlapping windows, and a note is reported once it has held steady for a
+/// few windows. The same note isn't reported again until something else (or
+/// silence) is detected in between.
+#[derive(Debug)]
+pub struct PitchTracker {
+    sample_rate: u32,
+    a4_hz: f64,
+    buffer: Vec<f32>,
+    candidate: Option<i32>,
+    candidate_count: usize,
+    reported: Option<i32>,
+}
+
+impl PitchTracker {
+    pub fn new(sample_rate: u32, a4_hz: f64) -> PitchTracker {
+        PitchTracker {
+            sample_rate,
+            a4_hz,
+            buffer: vec![],
+            candidate: None,
+            candidate_count: 0,
+            reported: None,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Enough samples to compare two periods of the lowest detectable pitch.
+    fn window_len(&self) -> usize {
+        (2.5 * self.sample_rate as f64 / MIN_HZ) as usize
+    }
+
+    /// Adds samples to the stream, returning the notes (and their deviation in
+    /// cents) that were played in them.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<(Note, f64)> {
+        self.buffer.extend_from_slice(samples);
+
+        let window_len = self.window_len();
+        let hop = window_len / 2;
+        let mut played = vec![];
+
+        while self.buffer.len() >= window_len {
+            let detected = detect_pitch(&self.buffer[..window_len], self.sample_rate)
+                .and_then(|hz| Note::from_frequency(hz, self.a4_hz));
+            if let Some(note) = self.track(detected) {
+                played.push(note);
+            }
+            self.buffer.drain(..hop);
+        }
+
+        played
+    }
+
+    fn track(&mut self, detected: Option<(Note, f64)>) -> Option<(Note, f64)> {
+        let midi = detected.map(|(note, _)| note.midi_num());
+
+        if midi == self.candidate {
+            self.candidate_count += 1;
+        } else {
+            self.candidate = midi;
+            self.candidate_count = 1;
+        }
+
+        if midi.is_none() {
+            self.reported = None;
+            return None;
+        }
+
+        if self.candidate_count >= STABLE_WINDOWS && self.reported != midi {
+            self.reported = midi;
+            return detected;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::pitch::*;
+    use crate::audio::synth;
+    use crate::theory::A4_HZ;
+    use std::str::FromStr;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    fn sine(hz: f64, secs: f64) -> Vec<f32> {
+        let n = (secs * SAMPLE_RATE as f64) as usize;
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                (0.5 * (2.0 * std::f64::consts::PI * hz * t).sin()) as f32
+            })
+            .collect()
+    }
+
+    fn cents_off(found: f64, expected: f64) -> f64 {
+        1200.0 * (found / expected).log2()
+    }
+
+    #[test]
+    fn test_detect_sine_pitch() {
+        for hz in [41.2, 82.41, 110.0, 196.0, 440.0, 659.26, 1318.5] {
+            let samples = sine(hz, 0.25);
+            let found = detect_pitch(&samples, SAMPLE_RATE).unwrap();
+            assert!(
+                cents_off(found, hz).abs() < 5.0,
+                "{} Hz found as {}",
+                hz,
+                found
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_plucked_pitch() {
+        for name in ["E2", "A2", "D3", "G3", "B3", "E4", "A4"] {
+            let expected = Note::from_str(name).unwrap().frequency(A4_HZ);
+            let samples = synth::pluck(expected, SAMPLE_RATE, 0.5);
+            let window = &samples[SAMPLE_RATE as usize / 10..];
+            let found = detect_pitch(window, SAMPLE_RATE).unwrap();
+            assert!(cents_off(found, expected).abs() < 10.0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_detect_silence_and_noise() {
+        assert!(detect_pitch(&vec![0.0; 8192], SAMPLE_RATE).is_none());
+        assert!(detect_pitch(&sine(440.0, 0.001), SAMPLE_RATE).is_none());
+
+        // a cheap deterministic noise source
+        let mut state = 1u32;
+        let noise: Vec<f32> = (0..8192)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect();
+        assert!(detect_pitch(&noise, SAMPLE_RATE).is_none());
+    }
+
+    #[test]
+    fn test_pitch_tracker() {
+        let mut tracker = PitchTracker::new(SAMPLE_RATE, A4_HZ);
+        let a4 = Note::from_str("A4").unwrap();
+        let e2 = Note::from_str("E2").unwrap();
+
+        let mut played = vec![];
+        // stream audio in small chunks, like a client would
+        let audio = [
+            sine(440.0, 0.5),
+            vec![0.0; 12_000],
+            sine(440.0, 0.5),
+            sine(82.41, 0.5),
+        ];
+        for chunk in audio.concat().chunks(1024) {
+            played.extend(tracker.push(chunk));
+        }
+
+        let notes: Vec<Note> = played.iter().map(|(note, _)| *note).collect();
+        assert_eq!(notes, vec![a4, a4, e2]);
+        assert!(played.iter().all(|(_, cents)| cents.abs() < 5.0));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::audio::{self, SAMPLE_RATE};
+use crate::theory::Note;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// How long a rendered note lasts.
+const NOTE_SECS: f64 = 1.5;
+
+/// Length of the fade out at the end of a note, to avoid a click.
+const FADE_SECS: f64 = 0.05;
+
+/// Energy kept by the string on each trip round the loop.
+const DECAY: f64 = 0.998;
+
+/// The pluck's noise burst is seeded so that a note always renders the same.
+const NOISE_SEED: u64 = 0;
+
+/// Synthesizes a plucked string at `hz` with the Karplus-Strong algorithm.
+///
+/// The loop is a delay line followed by a two-point average (half a sample of
+/// delay) and a first order allpass filter that supplies the fractional part of
+/// the delay, so the pitch is accurate even for high notes where the delay line
+/// is only a few samples long.
+pub fn pluck(hz: f64, sample_rate: u32, secs: f64) -> Vec<f32> {
+    let loop_delay = sample_rate as f64 / hz - 0.5;
+    // keep the allpass delay between 0.1 and 1.1 samples, where it is most
+    // accurate
+    let len = ((loop_delay - 0.1).floor() as usize).max(1);
+    let frac = loop_delay - len as f64;
+    let coeff = (1.0 - frac) / (1.0 + frac);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(NOISE_SEED);
+    let mut line: Vec<f64> = (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let mean = line.iter().sum::<f64>() / len as f64;
+    line.iter_mut().for_each(|s| *s -= mean);
+
+    let num_samples = (secs * sample_rate as f64) as usize;
+    let mut samples = Vec::with_capacity(num_samples);
+    let mut pos = 0;
+    let mut prev_read = 0.0;
+    let (mut ap_in, mut ap_out) = (0.0, 0.0);
+
+    for _ in 0..num_samples {
+        let read = line[pos];
+        let averaged = DECAY * 0.5 * (read + prev_read);
+        prev_read = read;
+
+        ap_out = coeff * averaged + ap_in - coeff * ap_out;
+        ap_in = averaged;
+
+        line[pos] = ap_out;
+        pos = (pos + 1) % len;
+        samples.push(ap_out);
+    }
+
+    let peak = samples.iter().fold(0.0f64, |peak, s| peak.max(s.abs()));
+    let gain = if peak > 0.0 { 0.8 / peak } else { 0.0 };
+    let fade_len = ((FADE_SECS * sample_rate as f64) as usize).min(num_samples);
+
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let fade = ((num_samples - i) as f64 / fade_len as f64).min(1.0);
+            (s * gain * fade) as f32
+        })
+        .collect()
+}
+
+/// Renders `note` as a plucked string WAV file at its concert pitch.
+pub fn render_note_wav(note: &Note, a4_hz: f64) -> Vec<u8> {
+    let samples = pluck(note.frequency(a4_hz), SAMPLE_RATE, NOTE_SECS);
+    audio::encode_wav(&samples, SAMPLE_RATE)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::synth::*;
+    use crate::theory::A4_HZ;
+    use std::str::FromStr;
+
+    /// Estimates the fundamental from the first autocorrelation peak, refined
+    /// with parabolic interpolation.
+    fn estimate_hz(samples: &[f32], sample_rate: u32) -> f64 {
+        let window = &samples[samples.len() / 4..samples.len() / 4 + 4096];
+        let corr = |lag: usize| -> f64 {
+            window[..window.len() - lag]
+                .iter()
+                .zip(&window[lag..])
+                .map(|(a, b)| (a * b) as f64)
+                .sum()
+        };
+
+        let max_lag = 2048;
+        let corrs: Vec<f64> = (0..max_lag).map(corr).collect();
+        let first_dip = (1..max_lag).find(|&i| corrs[i] < 0.0).unwrap();
+        let peak = (first_dip..max_lag - 1)
+            .max_by(|&a, &b| corrs[a].total_cmp(&corrs[b]))
+            .unwrap();
+
+        let (a, b, c) = (corrs[peak - 1], corrs[peak], corrs[peak + 1]);
+        let shift = 0.5 * (a - c) / (a - 2.0 * b + c);
+        sample_rate as f64 / (peak as f64 + shift)
+    }
+
+    #[test]
+    fn test_rendered_note_pitch() {
+        for name in ["E2", "A2", "G3", "A4", "E5", "B5"] {
+            let note = Note::from_str(name).unwrap();
+            let wav = render_note_wav(&note, A4_HZ);
+
+            let (sample_rate, samples) = audio::decode_wav(&wav).unwrap();
+            assert_eq!(sample_rate, SAMPLE_RATE);
+            assert_eq!(samples.len(), (NOTE_SECS * SAMPLE_RATE as f64) as usize);
+            assert!(samples.iter().all(|s| s.abs() <= 0.8 + 1e-3));
+
+            let expected = note.frequency(A4_HZ);
+            let cents = 1200.0 * (estimate_hz(&samples, sample_rate) / expected).log2();
+            assert!(cents.abs() < 5.0, "{} is {:.1} cents off", name, cents);
+        }
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let note = Note::from_str("C4").unwrap();
+        assert_eq!(render_note_wav(&note, A4_HZ), render_note_wav(&note, A4_HZ));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::app_state::{AppState, Room};
+use crate::game::db::{self, ChangeGameError};
+use crate::game::{Game, GameError, GameId, Status};
+use crate::ws::{self, ServerMessage};
+use sqlx::{Pool, Sqlite};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+/// Where the clock loads games from and changes them.
+pub trait GameStore: Send + Sync + 'static {
+    fn load(&self, game_id: GameId) -> impl Future<Output = Option<Game>> + Send;
+
+    /// Changes the game without racing any other change to it, see
+    /// `db::change_game`.
+    fn change<R: Send>(
+        &self,
+        game_id: GameId,
+        change: impl FnOnce(&mut Game) -> Result<R, GameError> + Send,
+    ) -> impl Future<Output = Result<(Game, R), ChangeGameError>> + Send;
+}
+
+/// The database, along with the lock of the game's room.
+pub struct RoomDb {
+    pub pool: Pool<Sqlite>,
+    pub lock: Arc<Mutex<()>>,
+}
+
+impl GameStore for RoomDb {
+    async fn load(&self, game_id: GameId) -> Option<Game> {
+        db::fetch_game(&self.pool, game_id).await.ok()
+    }
+
+    async fn change<R: Send>(
+        &self,
+        game_id: GameId,
+        change: impl FnOnce(&mut Game) -> Result<R, GameError> + Send,
+    ) -> Result<(Game, R), ChangeGameError> {
+        db::change_game(&self.pool, &self.lock, game_id, change).await
+    }
+}
+
+/// Starts the clock of a game, broadcasting to its room.
+pub fn start(pool: &Pool<Sqlite>, room: Room, game_id: GameId) -> JoinHandle<()> {
+    let store = RoomDb {
+        pool: pool.clone(),
+        lock: room.lock,
+    };
+    spawn(store, room.tx, room.clock, game_id)
+}
+
+/// Starts the clocks of games that were running when the server stopped.
+pub async fn resume(state: &AppState) -> Result<(), sqlx::Error> {
+    for game_id in db::fetch_running_game_ids(&state.pool).await? {
+        tracing::debug!("resuming clock of game {}", game_id);
+        start(&state.pool, state.room(game_id), game_id);
+    }
+    Ok(())
+}
+
+/// Runs a started game on the server: closes each round once
+/// `Opts::round_secs` are up, and `Opts::break_secs` after a round closes
+/// starts the next one or finishes the game. Every change is broadcast on
+/// `tx`. Notify `wake` when something else changes the game, like a guess
+/// ending the round early. The task ends with the game.
+pub fn spawn<S: GameStore>(
+    store: S,
+    tx: broadcast::Sender<String>,
+    wake: Arc<Notify>,
+    game_id: GameId,
+) -> JoinHandle<()> {
+    tokio::spawn(run(store, tx, wake, game_id))
+}
+
+async fn run<S: GameStore>(
+    store: S,
+    tx: broadcast::Sender<String>,
+    wake: Arc<Notify>,
+    game_id: GameId,
+) {
+    // the round and status the deadline is for, so a wake-up doesn't
+    // restart the countdown
+    let mut phase: Option<(usize, Status, Option<Instant>)> = None;
+
+    loop {
+        let Some(game) = store.load(game_id).await else {
+            tracing::error!("clock lost game {}", game_id);
+            return;
+        };
+        let round_num = game.rounds.len();
+
+        let limit = match game.status {
+            Status::Playing => game.opts.round_secs,
+            Status::RoundOver => Some(game.opts.break_secs),
+            Status::Init | Status::GameOver | Status::NoPlayers => return,
+        };
+        let deadline = match phase {
+            Some((n, status, deadline)) if n == round_num && status == game.status => deadline,
+            _ => limit.map(|secs| Instant::now() + Duration::from_secs(secs as u64)),
+        };
+        phase = Some((round_num, game.status, deadline));
+
+        let timed_out = match deadline {
+            Some(deadline) => tokio::select! {
+                _ = time::sleep_until(deadline) => true,
+                _ = wake.notified() => false,
+            },
+            None => {
+                wake.notified().await;
+                false
+            }
+        };
+        if timed_out {
+            advance(&store, &tx, game_id, round_num, game.status).await;
+        }
+    }
+}
+
+/// Moves the game on from `status` in round `round_num` if nothing else has
+/// yet. A guess can end the round just as its time runs out, and then the
+/// break still has to be waited out.
+async fn advance<S: GameStore>(
+    store: &S,
+    tx: &broadcast::Sender<String>,
+    game_id: GameId,
+    round_num: usize,
+    status: Status,
+) {
+    let changed = store
+        .change(game_id, |game| {
+            if game.rounds.len() != round_num || game.status != status {
+                return Ok(None);
+            }
+            match game.status {
+                Status::Playing => game.end_round()?,
+                Status::RoundOver if game.is_last_round() => game.finish()?,
+                Status::RoundOver => game.next_round()?,
+                _ => return Ok(None),
+            };
+
+            if let Some(standings) = game.final_standings() {
+                return Ok(Some(ServerMessage::GameOver { standings }));
+            }
+            Ok(Some(match game.status {
+                Status::RoundOver => ServerMessage::RoundOver {
+                    round: round_num,
+                    standings: game.standings(),
+                },
+                _ => ServerMessage::RoundStarted {
+                    round: game.rounds.len(),
+                },
+            }))
+        })
+        .await;
+
+    match changed {
+        Ok((_, Some(msg))) => {
+            tracing::debug!("game {}: {:?}", game_id, &msg);
+            ws::send_server_message(tx, &msg);
+        }
+        // something else moved the game on first
+        Ok((_, None)) => {}
+        Err(e) => tracing::error!("clock can't advance game {}: {}", game_id, e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game::clock::*;
+
+    #[derive(Clone)]
+    struct MemoryStore(Arc<std::sync::Mutex<Game>>);
+
+    impl GameStore for MemoryStore {
+        async fn load(&self, _: GameId) -> Option<Game> {
+            Some(self.0.lock().unwrap().clone())
+        }
+
+        async fn change<R: Send>(
+            &self,
+            _: GameId,
+            change: impl FnOnce(&mut Game) -> Result<R, GameError> + Send,
+        ) -> Result<(Game, R), ChangeGameError> {
+            let mut game = self.0.lock().unwrap();
+            let result = change(&mut game).map_err(ChangeGameError::Game)?;
+            Ok((game.clone(), result))
+        }
+    }
+
+    fn status(store: &MemoryStore) -> (Status, usize) {
+        let game = store.0.lock().unwrap();
+        (game.status, game.rounds.len())
+    }
+
+    fn started_game(round_secs: Option<u32>) -> MemoryStore {
+        let mut game = Game::with_seed(1, 4);
+        game.opts.num_rounds = 2;
+        game.opts.round_secs = round_secs;
+        game.opts.break_secs = 3;
+        game.start().unwrap();
+        MemoryStore(Arc::new(std::sync::Mutex::new(game)))
+    }
+
+    async fn sleep_secs(secs: f64) {
+        time::sleep(Duration::from_secs_f64(secs)).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rounds_time_out() {
+        let store = started_game(Some(10));
+        let (tx, mut rx) = broadcast::channel(16);
+        let clock = spawn(store.clone(), tx, Arc::new(Notify::new()), 1);
+
+        sleep_secs(9.5).await;
+        assert_eq!(status(&store), (Status::Playing, 1));
+        sleep_secs(1.0).await;
+        assert_eq!(status(&store), (Status::RoundOver, 1));
+        assert!(rx.recv().await.unwrap().contains("RoundOver"));
+
+        sleep_secs(3.0).await;
+        assert_eq!(status(&store), (Status::Playing, 2));
+        assert!(rx.recv().await.unwrap().contains("RoundStarted"));
+
+        sleep_secs(13.0).await;
+        assert_eq!(status(&store), (Status::GameOver, 2));
+        assert!(rx.recv().await.unwrap().contains("RoundOver"));
+        assert!(rx.recv().await.unwrap().contains("GameOver"));
+
+        clock.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wake_after_guess() {
+        // no time limit, so only a correct guess ends the round
+        let store = started_game(None);
+        let (tx, _rx) = broadcast::channel(16);
+        let wake = Arc::new(Notify::new());
+        let clock = spawn(store.clone(), tx, wake.clone(), 1);
+
+        sleep_secs(100.0).await;
+        assert_eq!(status(&store), (Status::Playing, 1));
+
+        {
+            let mut game = store.0.lock().unwrap();
+            let note = game.current_note_to_guess().unwrap();
+            let coord = game.opts.fretboard().positions_of(note)[0];
+            assert_eq!(game.submit_guess(1, coord), Ok(true));
+        }
+        wake.notify_one();
+
+        sleep_secs(2.5).await;
+        assert_eq!(status(&store), (Status::RoundOver, 1));
+        sleep_secs(1.0).await;
+        assert_eq!(status(&store), (Status::Playing, 2));
+
+        store.0.lock().unwrap().abandon().unwrap();
+        wake.notify_one();
+        clock.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_guess_at_deadline() {
+        for _ in 0..20 {
+            let store = started_game(Some(10));
+            let (tx, _rx) = broadcast::channel(16);
+            let wake = Arc::new(Notify::new());
+            let clock = spawn(store.clone(), tx, wake.clone(), 1);
+
+            // the guess ends the round just as its time runs out, so the
+            // clock may see either first
+            sleep_secs(10.0).await;
+            store.0.lock().unwrap().end_round().unwrap();
+            wake.notify_one();
+
+            sleep_secs(2.5).await;
+            assert_eq!(status(&store), (Status::RoundOver, 1));
+            sleep_secs(1.0).await;
+            assert_eq!(status(&store), (Status::Playing, 2));
+
+            store.0.lock().unwrap().abandon().unwrap();
+            wake.notify_one();
+            clock.await.unwrap();
+        }
+    }
+}
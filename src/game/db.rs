@@ -1,6 +1,8 @@
-use crate::game::{Game, GameId};
+use crate::game::{Game, GameError, GameId, Status};
 use sqlx::sqlite::SqliteQueryResult;
 use sqlx::{Pool, Sqlite};
+use std::{error, fmt};
+use tokio::sync::Mutex;
 
 pub async fn fetch_game(pool: &Pool<Sqlite>, game_id: GameId) -> Result<Game, sqlx::Error> {
     sqlx::query_as::<_, Game>("SELECT * FROM games WHERE id = ?")
@@ -11,13 +13,14 @@ pub async fn fetch_game(pool: &Pool<Sqlite>, game_id: GameId) -> Result<Game, sq
 
 pub async fn insert_game(pool: &Pool<Sqlite>, game: Game) -> Result<GameId, sqlx::Error> {
     let game_id = sqlx::query(
-        "INSERT INTO games (host_id, status, player_ids, opts, rounds) VALUES (?, ?, ?, ?, ?);",
+        "INSERT INTO games (host_id, status, player_ids, opts, rounds, seed) VALUES (?, ?, ?, ?, ?, ?);",
     )
     .bind(game.host_id)
     .bind(game.status)
     .bind(serde_json::to_string(&game.player_ids).unwrap())
     .bind(serde_json::to_string(&game.opts).unwrap())
     .bind(serde_json::to_string(&game.rounds).unwrap())
+    .bind(game.seed)
     .execute(pool)
     .await?
     .last_insert_rowid();
@@ -27,18 +30,79 @@ pub async fn insert_game(pool: &Pool<Sqlite>, game: Game) -> Result<GameId, sqlx
 
 pub async fn update_game(
     pool: &Pool<Sqlite>,
-    game: Game,
+    game: &Game,
 ) -> Result<SqliteQueryResult, sqlx::Error> {
-    sqlx::query("UPDATE games SET host_id = ?, status = ?, player_ids = ?, opts = ?, rounds = ?);")
+    sqlx::query(
+        "UPDATE games SET host_id = ?, status = ?, player_ids = ?, opts = ?, rounds = ? WHERE id = ?;",
+    )
         .bind(game.host_id)
         .bind(game.status)
         .bind(serde_json::to_string(&game.player_ids).unwrap())
         .bind(serde_json::to_string(&game.opts).unwrap())
         .bind(serde_json::to_string(&game.rounds).unwrap())
+        .bind(game.id)
         .execute(pool)
         .await
 }
 
+/// Ids of the games with a round being played or just finished.
+pub async fn fetch_running_game_ids(pool: &Pool<Sqlite>) -> Result<Vec<GameId>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM games WHERE status IN (?, ?)")
+        .bind(Status::Playing)
+        .bind(Status::RoundOver)
+        .fetch_all(pool)
+        .await
+}
+
+#[derive(Debug)]
+pub enum ChangeGameError {
+    Fetch(sqlx::Error),
+    Game(GameError),
+    Save(sqlx::Error),
+}
+
+impl fmt::Display for ChangeGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fetch(e) => write!(f, "can't fetch game: {}", e),
+            Self::Game(e) => write!(f, "{}", e),
+            Self::Save(e) => write!(f, "can't save game: {}", e),
+        }
+    }
+}
+
+impl error::Error for ChangeGameError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Fetch(e) | Self::Save(e) => Some(e),
+            Self::Game(e) => Some(e),
+        }
+    }
+}
+
+/// Fetches a game, changes it and saves it while holding `lock`, the game's
+/// room lock, so that changes to a game happen one at a time and none of
+/// them overwrites another. Nothing is saved when `change` fails. Returns the
+/// changed game along with what `change` returned.
+pub async fn change_game<R>(
+    pool: &Pool<Sqlite>,
+    lock: &Mutex<()>,
+    game_id: GameId,
+    change: impl FnOnce(&mut Game) -> Result<R, GameError>,
+) -> Result<(Game, R), ChangeGameError> {
+    let _guard = lock.lock().await;
+
+    let mut game = fetch_game(pool, game_id)
+        .await
+        .map_err(ChangeGameError::Fetch)?;
+    let result = change(&mut game).map_err(ChangeGameError::Game)?;
+    update_game(pool, &game)
+        .await
+        .map_err(ChangeGameError::Save)?;
+
+    Ok((game, result))
+}
+
 // let mut tx = pool.begin().await?;
 // .execute(&mut *tx)
 // tx.commit().await?;
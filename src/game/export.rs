@@ -0,0 +1,298 @@
+use crate::game::Game;
+use crate::midi::{self, Track, TICKS_PER_QUARTER};
+use crate::user::UserId;
+
+/// Rounds without timestamps take up one bar of 4/4 each.
+const TICKS_PER_ROUND: u32 = 4 * TICKS_PER_QUARTER as u32;
+/// Milliseconds per quarter note at the 120 bpm `midi::write_smf` writes.
+const MS_PER_QUARTER: i64 = 500;
+/// Longest a guess sounds for.
+const GUESS_TICKS: u32 = TICKS_PER_QUARTER as u32;
+
+const TARGET_CHANNEL: u8 = 0;
+/// Channels handed out to players in turn, skipping the targets' channel and
+/// channel 10, which General MIDI keeps for drums. Past 14 players, channels
+/// are shared.
+const PLAYER_CHANNELS: [u8; 14] = [1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13, 14, 15];
+const CORRECT_VELOCITY: u8 = 100;
+const WRONG_VELOCITY: u8 = 60;
+
+fn ms_to_ticks(ms: i64) -> u32 {
+    let ticks = ms.max(0) * TICKS_PER_QUARTER as i64 / MS_PER_QUARTER;
+    u32::try_from(ticks).unwrap_or(u32::MAX)
+}
+
+/// Where each round starts, in ticks, followed by where the last one ends.
+/// Timed rounds start when they were played, and the last one lasts until
+/// its last guess has sounded. A round that seems to start before the one
+/// ahead of it, say because the server's clock was set back, starts with
+/// it instead. Games saved before rounds were timed get a bar per round.
+fn round_bounds(game: &Game) -> Vec<u32> {
+    let starts: Option<Vec<i64>> = game.rounds.iter().map(|r| r.started_at).collect();
+    let Some(starts) = starts.filter(|starts| !starts.is_empty()) else {
+        return (0..=game.rounds.len() as u32)
+            .map(|i| i * TICKS_PER_ROUND)
+            .collect();
+    };
+    let first = starts[0];
+
+    let mut bounds: Vec<u32> = starts
+        .iter()
+        .scan(0, |latest, &t| {
+            *latest = ms_to_ticks(t - first).max(*latest);
+            Some(*latest)
+        })
+        .collect();
+    let last_start = *bounds.last().unwrap_or(&0);
+    let last_guess = game
+        .rounds
+        .last()
+        .into_iter()
+        .flat_map(|round| round.guesses.iter().filter_map(|g| g.at))
+        .map(|at| ms_to_ticks(at - first).saturating_add(GUESS_TICKS))
+        .max()
+        .unwrap_or(0);
+    bounds.push(last_guess.max(last_start.saturating_add(TICKS_PER_ROUND)));
+    bounds
+}
+
+/// Exports the game as a Standard MIDI File. The first track holds the note to
+/// guess in each round, the rest hold each player's guesses, with correct
+/// guesses played louder than wrong ones. Guesses sound when they were made;
+/// in rounds without timestamps they're spread evenly across the round in
+/// the order they were made.
+pub fn game_to_midi(game: &Game) -> Vec<u8> {
+    let fretboard = game.opts.fretboard();
+    let bounds = round_bounds(game);
+
+    let mut targets = Track::new("Notes to guess");
+    for (i, round) in game.rounds.iter().enumerate() {
+        targets.add_note(
+            TARGET_CHANNEL,
+            round.note_to_guess.midi_num(),
+            CORRECT_VELOCITY,
+            bounds[i],
+            bounds[i + 1].saturating_sub(bounds[i]).max(1),
+        );
+    }
+
+    let mut user_ids: Vec<UserId> = game.player_ids.clone();
+    let guessers = game
+        .rounds
+        .iter()
+        .flat_map(|round| round.guesses.iter().filter_map(|g| g.user_id));
+    for user_id in guessers {
+        if !user_ids.contains(&user_id) {
+            user_ids.push(user_id);
+        }
+    }
+
+    let mut tracks = vec![targets];
+    for (i, user_id) in user_ids.iter().enumerate() {
+        let channel = PLAYER_CHANNELS[i % PLAYER_CHANNELS.len()];
+        let mut track = Track::new(&format!("Player {}", user_id));
+
+        for (round_num, round) in game.rounds.iter().enumerate() {
+            let guesses: Vec<_> = round
+                .guesses
+                .iter()
+                .filter(|g| g.user_id == Some(*user_id))
+                .collect();
+            if guesses.is_empty() {
+                continue;
+            }
+
+            let (round_start, round_end) = (bounds[round_num], bounds[round_num + 1]);
+            let spacing = round_end.saturating_sub(round_start) / guesses.len() as u32;
+
+            for (j, guess) in guesses.iter().enumerate() {
+                let Some(note) = fretboard.note_at(&guess.clicked_fret) else {
+                    continue;
+                };
+                let (start, len) = match (guess.at, round.started_at) {
+                    (Some(at), Some(started_at)) => (
+                        round_start.saturating_add(ms_to_ticks(at - started_at)),
+                        GUESS_TICKS,
+                    ),
+                    _ => (round_start + j as u32 * spacing, spacing.max(1)),
+                };
+                let velocity = if guess.is_correct {
+                    CORRECT_VELOCITY
+                } else {
+                    WRONG_VELOCITY
+                };
+                track.add_note(channel, note.midi_num(), velocity, start, len);
+            }
+        }
+
+        tracks.push(track);
+    }
+
+    midi::write_smf(&tracks)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game::export::*;
+    use crate::game::{Guess, Round};
+    use crate::theory::{FretCoord, Note};
+
+    /// Returns (tick, channel, key, velocity) for each note on in a track
+    /// chunk.
+    fn timed_note_ons(track: &[u8]) -> Vec<(u32, u8, u8, u8)> {
+        let mut ons = vec![];
+        let mut tick = 0;
+        let mut i = 8;
+        while i < track.len() {
+            let mut delta = 0;
+            while track[i] & 0x80 != 0 {
+                delta = (delta << 7) | (track[i] & 0x7f) as u32;
+                i += 1;
+            }
+            tick += (delta << 7) | track[i] as u32;
+            i += 1;
+
+            match track[i] {
+                0xff => i += 3 + track[i + 2] as usize,
+                status if status & 0xf0 == 0x90 => {
+                    ons.push((tick, status & 0x0f, track[i + 1], track[i + 2]));
+                    i += 3;
+                }
+                _ => i += 3,
+            }
+        }
+        ons
+    }
+
+    /// Returns (channel, key, velocity) for each note on in a track chunk.
+    fn note_ons(track: &[u8]) -> Vec<(u8, u8, u8)> {
+        timed_note_ons(track)
+            .into_iter()
+            .map(|(_, channel, key, velocity)| (channel, key, velocity))
+            .collect()
+    }
+
+    fn tracks(smf: &[u8]) -> Vec<&[u8]> {
+        let mut tracks = vec![];
+        let mut rest = &smf[14..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[4..8].try_into().unwrap()) as usize;
+            tracks.push(&rest[..8 + len]);
+            rest = &rest[8 + len..];
+        }
+        tracks
+    }
+
+    fn guess(user_id: UserId, string: i32, fret: i32, is_correct: bool) -> Guess {
+        Guess {
+            user_id: Some(user_id),
+            clicked_fret: FretCoord { string, fret },
+            is_correct,
+            at: None,
+        }
+    }
+
+    #[test]
+    fn test_game_to_midi() {
+        let mut game = Game::with_seed(1, 0);
+        game.player_ids.push(2);
+        game.rounds = vec![
+            Round {
+                note_to_guess: Note::from_midi(64),
+                guesses: vec![guess(1, 1, 0, true), guess(2, 2, 3, false)],
+                started_at: None,
+            },
+            Round {
+                note_to_guess: Note::from_midi(45),
+                guesses: vec![guess(2, 6, 4, false), guess(2, 5, 0, true)],
+                started_at: None,
+            },
+        ];
+
+        let smf = game_to_midi(&game);
+        assert_eq!(&smf[..4], b"MThd");
+
+        let tracks = tracks(&smf);
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(note_ons(tracks[0]), vec![(0, 64, 100), (0, 45, 100)]);
+        assert_eq!(note_ons(tracks[1]), vec![(1, 64, 100)]);
+        assert_eq!(
+            note_ons(tracks[2]),
+            vec![(2, 62, 60), (2, 44, 60), (2, 45, 100)]
+        );
+    }
+
+    #[test]
+    fn test_timed_guesses() {
+        let mut game = Game::with_seed(1, 0);
+        let timed = |mut guess: Guess, at| {
+            guess.at = Some(at);
+            guess
+        };
+        game.rounds = vec![
+            Round {
+                note_to_guess: Note::from_midi(64),
+                guesses: vec![timed(guess(1, 1, 1, false), 10_250)],
+                started_at: Some(10_000),
+            },
+            Round {
+                note_to_guess: Note::from_midi(45),
+                guesses: vec![timed(guess(1, 5, 0, true), 16_000)],
+                started_at: Some(15_000),
+            },
+        ];
+
+        let smf = game_to_midi(&game);
+        let tracks = tracks(&smf);
+        // 480 ticks to a 500ms quarter note
+        let ticks: Vec<u32> = timed_note_ons(tracks[0]).iter().map(|n| n.0).collect();
+        assert_eq!(ticks, vec![0, 4800]);
+        let ticks: Vec<u32> = timed_note_ons(tracks[1]).iter().map(|n| n.0).collect();
+        assert_eq!(ticks, vec![240, 4800 + 960]);
+    }
+
+    #[test]
+    fn test_clock_set_back() {
+        let mut game = Game::with_seed(1, 0);
+        let round = |midi, guesses, started_at| Round {
+            note_to_guess: Note::from_midi(midi),
+            guesses,
+            started_at: Some(started_at),
+        };
+        game.rounds = vec![
+            round(64, vec![], 10_000),
+            round(45, vec![guess(1, 5, 0, true)], 15_000),
+            round(52, vec![], 12_000),
+        ];
+
+        let smf = game_to_midi(&game);
+        let tracks = tracks(&smf);
+        let ticks: Vec<u32> = timed_note_ons(tracks[0]).iter().map(|n| n.0).collect();
+        assert_eq!(ticks, vec![0, 4800, 4800]);
+        let ticks: Vec<u32> = timed_note_ons(tracks[1]).iter().map(|n| n.0).collect();
+        assert_eq!(ticks, vec![4800]);
+    }
+
+    #[test]
+    fn test_player_channels() {
+        let mut game = Game::with_seed(1, 0);
+        game.player_ids = (1..=20).collect();
+        game.rounds = vec![Round {
+            note_to_guess: Note::from_midi(64),
+            guesses: (1..=20).map(|id| guess(id, 1, 0, true)).collect(),
+            started_at: None,
+        }];
+
+        let smf = game_to_midi(&game);
+        let tracks = tracks(&smf);
+        assert_eq!(tracks.len(), 21);
+
+        let channels: Vec<u8> = tracks[1..]
+            .iter()
+            .map(|track| note_ons(track)[0].0)
+            .collect();
+        assert!(channels.iter().all(|&c| c != TARGET_CHANNEL && c != 9));
+        assert_eq!(&channels[..10], &[1, 2, 3, 4, 5, 6, 7, 8, 10, 11]);
+        assert_eq!(channels[14], channels[0]);
+    }
+}
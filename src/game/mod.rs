@@ -1,14 +1,31 @@
+pub mod clock;
 pub mod db;
+pub mod export;
+pub mod score;
 
-use crate::theory::{self, FretCoord, Note};
+use crate::theory::clef::Clef;
+use crate::theory::key::Key;
+use crate::theory::{self, Capo, FretCoord, Fretboard, Note, StringSpec, Tuning, TuningPreset};
 use crate::user::UserId;
 
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{error, fmt};
 
 pub type GameId = i64;
 
-#[derive(Debug, sqlx::FromRow)]
+/// Milliseconds since the Unix epoch.
+pub type Timestamp = i64;
+
+pub fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as Timestamp)
+}
+
+#[derive(Clone, Debug, sqlx::FromRow)]
 pub struct Game {
     pub id: Option<GameId>,
     pub host_id: Option<UserId>,
@@ -19,10 +36,17 @@ pub struct Game {
     pub opts: Opts,
     #[sqlx(json)]
     pub rounds: Vec<Round>,
+    /// Every note of the game is generated from this, so storing it is enough
+    /// to replay the game.
+    pub seed: i64,
 }
 
 impl Game {
     pub fn new(host_id: UserId) -> Self {
+        Self::with_seed(host_id, rand::random())
+    }
+
+    pub fn with_seed(host_id: UserId, seed: i64) -> Self {
         Game {
             id: None,
             host_id: Some(host_id),
@@ -30,24 +54,365 @@ impl Game {
             opts: Opts::new(),
             rounds: vec![],
             player_ids: vec![host_id],
+            seed,
         }
     }
 
+    /// The rng for a round depends only on the seed and the round number, so a
+    /// round's note doesn't change with what happened in earlier rounds.
+    fn round_rng(&self, round_num: usize) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed as u64);
+        rng.set_stream(round_num as u64);
+        rng
+    }
+
+    /// The notes to guess for every round of the game, in order.
+    pub fn note_sequence(&self) -> Vec<Note> {
+        (0..self.opts.num_rounds.max(0) as usize)
+            .map(|i| Round::new(&self.opts, &mut self.round_rng(i)).note_to_guess)
+            .collect()
+    }
+
     pub fn current_note_to_guess(&self) -> Option<Note> {
         self.rounds.last().map(|r| r.note_to_guess)
     }
 
-    pub fn start(&mut self) {
-        self.status = Status::Playing;
-        self.rounds.push(Round::new());
+    /// Whether the round being played or just finished is the game's last.
+    pub fn is_last_round(&self) -> bool {
+        self.rounds.len() >= self.opts.num_rounds.max(0) as usize
+    }
+
+    /// Moves to `to` if the game is in `from`, otherwise leaves it as it is.
+    fn transition(&mut self, from: &[Status], to: Status) -> Result<(), GameError> {
+        if !from.contains(&self.status) {
+            return Err(GameError::IllegalTransition {
+                from: self.status,
+                to,
+            });
+        }
+        self.status = to;
+        Ok(())
+    }
+
+    fn push_round(&mut self) {
+        let mut rng = self.round_rng(self.rounds.len());
+        let mut round = Round::new(&self.opts, &mut rng);
+        round.started_at = Some(now());
+        self.rounds.push(round);
+    }
+
+    /// Starts the first round.
+    pub fn start(&mut self) -> Result<(), GameError> {
+        if self.status == Status::Init && self.opts.num_rounds < 1 {
+            return Err(GameError::NoRounds);
+        }
+        self.transition(&[Status::Init], Status::Playing)?;
+        self.push_round();
+        Ok(())
+    }
+
+    /// Stops taking guesses for the current round.
+    pub fn end_round(&mut self) -> Result<(), GameError> {
+        self.transition(&[Status::Playing], Status::RoundOver)
+    }
+
+    /// Starts the round after the one that just ended. The last round can
+    /// only be followed by `finish`.
+    pub fn next_round(&mut self) -> Result<(), GameError> {
+        if self.status == Status::RoundOver && self.is_last_round() {
+            return Err(GameError::NoRoundsLeft);
+        }
+        self.transition(&[Status::RoundOver], Status::Playing)?;
+        self.push_round();
+        Ok(())
+    }
+
+    /// Ends the game once its last round is over.
+    pub fn finish(&mut self) -> Result<(), GameError> {
+        if self.status == Status::RoundOver && !self.is_last_round() {
+            return Err(GameError::RoundsLeft);
+        }
+        self.transition(&[Status::RoundOver], Status::GameOver)
+    }
+
+    /// Grades a click on the fretboard against the current round's note and
+    /// records it, ending the round if that satisfies `Opts::round_end`.
+    /// Returns whether the guess was correct.
+    pub fn submit_guess(&mut self, user_id: UserId, coord: FretCoord) -> Result<bool, GameError> {
+        self.submit_guess_at(user_id, coord, now())
+    }
+
+    /// `submit_guess` for a guess made at `at`.
+    pub(crate) fn submit_guess_at(
+        &mut self,
+        user_id: UserId,
+        coord: FretCoord,
+        at: Timestamp,
+    ) -> Result<bool, GameError> {
+        self.check_guesser(user_id)?;
+        let note = self
+            .opts
+            .fretboard()
+            .note_at(&coord)
+            .ok_or(GameError::OffTheBoard(coord))?;
+
+        let opts = &self.opts;
+        let round = self
+            .rounds
+            .last_mut()
+            .ok_or(GameError::NotPlaying(self.status))?;
+        if round.is_done(user_id, opts.round_end) {
+            return Err(GameError::AlreadyGuessed(user_id));
+        }
+
+        let is_correct = opts.octave_rule.matches(note, round.note_to_guess);
+        round.guesses.push(Guess {
+            user_id: Some(user_id),
+            clicked_fret: coord,
+            is_correct,
+            at: Some(at),
+        });
+
+        if self.round_is_over() {
+            self.end_round()?;
+        }
+
+        Ok(is_correct)
+    }
+
+    /// Grades and records a note played on an instrument, like
+    /// `submit_guess` for the fretboard position it was played at: on
+    /// `string` when the instrument says, otherwise the first position on
+    /// the board with the same pitch.
+    pub fn submit_note_guess(
+        &mut self,
+        user_id: UserId,
+        note: Note,
+        string: Option<i32>,
+    ) -> Result<bool, GameError> {
+        self.submit_note_guess_at(user_id, note, string, now())
+    }
+
+    /// `submit_note_guess` for a note played at `at`.
+    pub(crate) fn submit_note_guess_at(
+        &mut self,
+        user_id: UserId,
+        note: Note,
+        string: Option<i32>,
+        at: Timestamp,
+    ) -> Result<bool, GameError> {
+        self.check_guesser(user_id)?;
+        let coord = self
+            .opts
+            .fretboard()
+            .positions_of(note)
+            .into_iter()
+            .find(|coord| string.is_none_or(|string| coord.string == string))
+            .ok_or(GameError::NotOnTheBoard {
+                midi: note.midi_num(),
+                string,
+            })?;
+
+        self.submit_guess_at(user_id, coord, at)
+    }
+
+    fn check_guesser(&self, user_id: UserId) -> Result<(), GameError> {
+        if self.status != Status::Playing {
+            return Err(GameError::NotPlaying(self.status));
+        }
+        if !self.player_ids.contains(&user_id) {
+            return Err(GameError::NotAPlayer(user_id));
+        }
+        Ok(())
+    }
+
+    /// Whether the current round's guesses satisfy `Opts::round_end`.
+    fn round_is_over(&self) -> bool {
+        let Some(round) = self.rounds.last() else {
+            return false;
+        };
+        match self.opts.round_end {
+            RoundEnd::FirstCorrect => round.guesses.iter().any(|g| g.is_correct),
+            RoundEnd::AllCorrect | RoundEnd::AllGuessed => self
+                .player_ids
+                .iter()
+                .all(|&id| round.is_done(id, self.opts.round_end)),
+        }
+    }
+
+    /// Adds a player, if there's a seat left and `Opts::late_join` lets
+    /// them in at this point of the game.
+    pub fn join(&mut self, user_id: UserId) -> Result<(), GameError> {
+        let open = match self.status {
+            Status::Init => true,
+            Status::Playing => self.opts.late_join == LateJoin::Anytime,
+            Status::RoundOver => self.opts.late_join != LateJoin::Never,
+            Status::GameOver | Status::NoPlayers => false,
+        };
+        if !open {
+            return Err(GameError::JoinClosed(self.status));
+        }
+        if self.player_ids.contains(&user_id) {
+            return Err(GameError::AlreadyJoined(user_id));
+        }
+        if self.player_ids.len() >= self.opts.max_players as usize {
+            return Err(GameError::GameFull);
+        }
+
+        self.player_ids.push(user_id);
+        Ok(())
+    }
+
+    /// Removes a player from a game that isn't over. The next player in line
+    /// takes over as host if the host leaves, and the game is abandoned when
+    /// the last one does. A round that was only waiting on the player ends.
+    pub fn leave(&mut self, user_id: UserId) -> Result<(), GameError> {
+        if matches!(self.status, Status::GameOver | Status::NoPlayers) {
+            return Err(GameError::JoinClosed(self.status));
+        }
+        let Some(i) = self.player_ids.iter().position(|&id| id == user_id) else {
+            return Err(GameError::NotAPlayer(user_id));
+        };
+
+        self.player_ids.remove(i);
+        // an abandoned game keeps its last host
+        if self.host_id == Some(user_id) && !self.player_ids.is_empty() {
+            self.host_id = Some(self.player_ids[0]);
+        }
+
+        if self.player_ids.is_empty() {
+            self.abandon()
+        } else if self.status == Status::Playing && self.round_is_over() {
+            self.end_round()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Every player's score so far, leader first.
+    pub fn standings(&self) -> Vec<score::Standing> {
+        score::standings(self)
+    }
+
+    /// The standings the game ended with, once it's over.
+    pub fn final_standings(&self) -> Option<Vec<score::Standing>> {
+        (self.status == Status::GameOver).then(|| self.standings())
+    }
+
+    /// Ends a game that hasn't finished because everyone left.
+    pub fn abandon(&mut self) -> Result<(), GameError> {
+        self.transition(
+            &[Status::Init, Status::Playing, Status::RoundOver],
+            Status::NoPlayers,
+        )
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum GameError {
+    /// The game can't go from `from` to `to`.
+    IllegalTransition {
+        from: Status,
+        to: Status,
+    },
+    /// `Opts::num_rounds` is less than one, so there's nothing to play.
+    NoRounds,
+    /// The round that ended was the last one, so the game can only finish.
+    NoRoundsLeft,
+    /// The game can't finish before its last round is over.
+    RoundsLeft,
+    /// Guesses are only taken while a round is being played.
+    NotPlaying(Status),
+    NotAPlayer(UserId),
+    /// The guess isn't a playable position on the game's fretboard.
+    OffTheBoard(FretCoord),
+    /// The played note can't be found on the game's fretboard, or on the
+    /// string it was played on.
+    NotOnTheBoard {
+        midi: i32,
+        string: Option<i32>,
+    },
+    /// The player has no guesses left this round.
+    AlreadyGuessed(UserId),
+    /// Players can't join or leave the game in this status.
+    JoinClosed(Status),
+    AlreadyJoined(UserId),
+    /// Every seat set by `Opts::max_players` is taken.
+    GameFull,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IllegalTransition { from, to } => {
+                write!(f, "can't go from {} to {}", from, to)
+            }
+            Self::NoRounds => write!(f, "the game has no rounds"),
+            Self::NoRoundsLeft => write!(f, "the last round has been played"),
+            Self::RoundsLeft => write!(f, "there are rounds left to play"),
+            Self::NotPlaying(status) => write!(f, "can't guess while the game is {}", status),
+            Self::NotAPlayer(user_id) => write!(f, "user {} isn't in the game", user_id),
+            Self::OffTheBoard(coord) => {
+                write!(
+                    f,
+                    "string {} fret {} isn't on the fretboard",
+                    coord.string, coord.fret
+                )
+            }
+            Self::NotOnTheBoard { midi, string } => match string {
+                Some(string) => write!(
+                    f,
+                    "midi note {} isn't on string {} of the fretboard",
+                    midi, string
+                ),
+                None => write!(f, "midi note {} isn't on the fretboard", midi),
+            },
+            Self::AlreadyGuessed(user_id) => {
+                write!(f, "user {} can't guess again this round", user_id)
+            }
+            Self::JoinClosed(status) => {
+                write!(
+                    f,
+                    "players can't join or leave while the game is {}",
+                    status
+                )
+            }
+            Self::AlreadyJoined(user_id) => write!(f, "user {} is already in the game", user_id),
+            Self::GameFull => write!(f, "the game is full"),
+        }
+    }
+}
+
+impl error::Error for GameError {}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Opts {
     pub num_rounds: i32,
+    /// Fret window, counted from the capo on strings it covers.
     pub start_fret: i32,
     pub end_fret: i32,
+    pub tuning: Tuning,
+    pub capo: Option<Capo>,
+    /// Indexed like the tuning, see `Fretboard::with_strings`.
+    pub strings: Vec<StringSpec>,
+    /// Restricts generated notes to this key and spells them for it.
+    pub key: Option<Key>,
+    /// With a key set, also generate notes outside of the key.
+    pub chromatic: bool,
+    /// How notes are written on the staff. Notes are generated and graded by
+    /// the pitch they sound at, the clef only decides where they're drawn.
+    pub clef: Clef,
+    pub octave_rule: OctaveRule,
+    pub round_end: RoundEnd,
+    /// Seconds a round lasts before the server closes it, None to wait for
+    /// `round_end`.
+    pub round_secs: Option<u32>,
+    /// Seconds between a round closing and the next one starting.
+    pub break_secs: u32,
+    /// Most players the game seats, the host included.
+    pub max_players: u32,
+    pub late_join: LateJoin,
 }
 
 impl Opts {
@@ -56,8 +421,26 @@ impl Opts {
             num_rounds: 4,
             start_fret: 0,
             end_fret: 4,
+            tuning: TuningPreset::Standard.tuning(),
+            capo: None,
+            strings: vec![],
+            key: None,
+            chromatic: false,
+            clef: Clef::Treble8vb,
+            octave_rule: OctaveRule::Exact,
+            round_end: RoundEnd::FirstCorrect,
+            round_secs: Some(30),
+            break_secs: 5,
+            max_players: 8,
+            late_join: LateJoin::BetweenRounds,
         }
     }
+
+    pub fn fretboard(&self) -> Fretboard {
+        Fretboard::new(self.tuning.clone(), self.start_fret, self.end_fret)
+            .with_capo(self.capo.clone())
+            .with_strings(self.strings.clone())
+    }
 }
 
 impl Default for Opts {
@@ -66,7 +449,85 @@ impl Default for Opts {
     }
 }
 
-#[derive(Debug, sqlx::Type)]
+/// How close a guess has to be to the note to count. Any spelling of the
+/// right pitch is accepted either way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OctaveRule {
+    /// Only the pitch the note sounds at.
+    Exact,
+    /// The note's pitch class in any octave.
+    Any,
+}
+
+impl OctaveRule {
+    pub const ALL: [OctaveRule; 2] = [Self::Exact, Self::Any];
+
+    pub fn matches(&self, guess: Note, target: Note) -> bool {
+        match self {
+            Self::Exact => guess.is_enharmonic(target),
+            Self::Any => (guess.midi_num() - target.midi_num()).rem_euclid(12) == 0,
+        }
+    }
+}
+
+impl fmt::Display for OctaveRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exact => write!(f, "Exact octave"),
+            Self::Any => write!(f, "Any octave"),
+        }
+    }
+}
+
+/// When a round stops taking guesses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RoundEnd {
+    /// As soon as anyone finds the note.
+    FirstCorrect,
+    /// Once every player has found the note.
+    AllCorrect,
+    /// Once every player has had one guess, right or wrong.
+    AllGuessed,
+}
+
+impl RoundEnd {
+    pub const ALL: [RoundEnd; 3] = [Self::FirstCorrect, Self::AllCorrect, Self::AllGuessed];
+}
+
+impl fmt::Display for RoundEnd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FirstCorrect => write!(f, "First to find it"),
+            Self::AllCorrect => write!(f, "Everyone finds it"),
+            Self::AllGuessed => write!(f, "One guess each"),
+        }
+    }
+}
+
+/// When players can join a game that has already started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LateJoin {
+    Never,
+    /// Only while a round is over, so nobody joins halfway through one.
+    BetweenRounds,
+    Anytime,
+}
+
+impl LateJoin {
+    pub const ALL: [LateJoin; 3] = [Self::Never, Self::BetweenRounds, Self::Anytime];
+}
+
+impl fmt::Display for LateJoin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Never => write!(f, "Never"),
+            Self::BetweenRounds => write!(f, "Between rounds"),
+            Self::Anytime => write!(f, "Anytime"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
 pub enum Status {
     Init,
     Playing,
@@ -75,30 +536,447 @@ pub enum Status {
     NoPlayers,
 }
 
+impl Status {
+    pub const ALL: [Status; 5] = [
+        Status::Init,
+        Status::Playing,
+        Status::RoundOver,
+        Status::GameOver,
+        Status::NoPlayers,
+    ];
+}
+
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Guess {
     user_id: Option<i64>,
     clicked_fret: FretCoord,
     is_correct: bool,
+    /// When the guess was made. Unknown for games saved before guesses were
+    /// timed.
+    #[serde(default)]
+    at: Option<Timestamp>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Round {
+    /// The pitch the note sounds at, to find on the fretboard.
     pub note_to_guess: Note,
     pub guesses: Vec<Guess>,
+    #[serde(default)]
+    pub started_at: Option<Timestamp>,
 }
 
+/// Notes drawn for a round before settling for the lowest note on the board.
+const MAX_DRAWS: usize = 32;
+
 impl Round {
-    fn new() -> Round {
+    fn new<R: Rng + ?Sized>(opts: &Opts, rng: &mut R) -> Round {
+        let fretboard = opts.fretboard();
+        let (low, high) = fretboard.midi_range().unwrap_or((40, 68));
+
+        // skipped strings and missing frets can leave gaps in the range
+        let note_to_guess = (0..MAX_DRAWS)
+            .map(|_| {
+                opts.key
+                    .and_then(|key| key.rand_in_range(rng, low, high, opts.chromatic))
+                    .or_else(|| Note::rand_in_range(rng, low, high))
+            })
+            .find(|note| note.is_some_and(|n| !fretboard.positions_of(n).is_empty()))
+            .flatten()
+            .unwrap_or_else(|| Note::from_midi(low));
+
         Round {
-            note_to_guess: Note::rand_in_range(40, 68),
+            note_to_guess,
             guesses: vec![],
+            started_at: None,
+        }
+    }
+
+    /// Whether `user_id` is out of guesses: they found the note, or under
+    /// `RoundEnd::AllGuessed` had their one try.
+    fn is_done(&self, user_id: UserId, round_end: RoundEnd) -> bool {
+        self.guesses
+            .iter()
+            .filter(|g| g.user_id == Some(user_id))
+            .any(|g| g.is_correct || round_end == RoundEnd::AllGuessed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game::*;
+
+    fn note_strings(notes: &[Note]) -> Vec<String> {
+        notes.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_seeded_note_sequence() {
+        let game = Game::with_seed(1, 42);
+        let notes = game.note_sequence();
+        assert_eq!(notes.len(), game.opts.num_rounds as usize);
+        assert_eq!(
+            note_strings(&notes),
+            note_strings(&Game::with_seed(2, 42).note_sequence())
+        );
+
+        let mut game = Game::with_seed(1, 42);
+        game.start().unwrap();
+        assert_eq!(
+            game.current_note_to_guess().unwrap().to_string(),
+            notes[0].to_string()
+        );
+
+        let other = (0..10).any(|seed| {
+            note_strings(&Game::with_seed(1, seed).note_sequence()) != note_strings(&notes)
+        });
+        assert!(other);
+    }
+
+    #[test]
+    fn test_guitar_notes_fit_the_staff() {
+        // the open low E is written three ledger lines down and the 4th fret
+        // of the high E string two up, give or take a step for spellings like
+        // D##2 or Ab4
+        for seed in 0..20 {
+            let game = Game::with_seed(1, seed);
+            assert_eq!(game.opts.clef, Clef::Treble8vb);
+
+            for note in game.note_sequence() {
+                let step = game.opts.clef.staff_step(&note);
+                assert!((-8..=11).contains(&step), "{} at step {}", note, step);
+            }
+        }
+    }
+
+    #[test]
+    fn test_notes_are_playable() {
+        let mut game = Game::with_seed(1, 3);
+        game.opts.num_rounds = 50;
+        game.opts.start_fret = 5;
+        game.opts.end_fret = 9;
+        game.opts.strings = vec![StringSpec::default(); 6];
+        for spec in &mut game.opts.strings[..3] {
+            spec.skipped = true;
+        }
+        game.opts.strings[4].missing_frets = vec![7];
+
+        let fretboard = game.opts.fretboard();
+        for note in game.note_sequence() {
+            let positions = fretboard.positions_of(note);
+            assert!(!positions.is_empty(), "{}", note);
+            assert!(positions.iter().all(|c| c.string >= 4));
+        }
+    }
+
+    #[test]
+    fn test_narrow_range_note_generation() {
+        let mut game = Game::with_seed(1, 7);
+        game.opts.tuning = vec![Note::from_midi(60)];
+        game.opts.end_fret = 0;
+
+        for note in game.note_sequence() {
+            assert_eq!(note.midi_num(), 60);
+        }
+    }
+
+    type Action = fn(&mut Game) -> Result<(), GameError>;
+
+    const ACTIONS: [(&str, Action); 5] = [
+        ("start", Game::start),
+        ("end_round", Game::end_round),
+        ("next_round", Game::next_round),
+        ("finish", Game::finish),
+        ("abandon", Game::abandon),
+    ];
+
+    /// A two round game in `status`, with `rounds` of them started.
+    fn game_in(status: Status, rounds: usize) -> Game {
+        let mut game = Game::with_seed(1, 5);
+        game.opts.num_rounds = 2;
+        for _ in 0..rounds {
+            game.push_round();
+        }
+        game.status = status;
+        game
+    }
+
+    /// What each action does to a game in `status`, which is on its last
+    /// round if `last` is set.
+    fn expected(action: &str, status: Status, last: bool) -> Result<Status, GameError> {
+        use Status::*;
+        let to = match action {
+            "start" => Playing,
+            "end_round" => RoundOver,
+            "next_round" => Playing,
+            "finish" => GameOver,
+            _ => NoPlayers,
+        };
+        match (action, status) {
+            ("start", Init) | ("end_round", Playing) => Ok(to),
+            ("next_round", RoundOver) if last => Err(GameError::NoRoundsLeft),
+            ("next_round", RoundOver) => Ok(to),
+            ("finish", RoundOver) if last => Ok(to),
+            ("finish", RoundOver) => Err(GameError::RoundsLeft),
+            ("abandon", Init | Playing | RoundOver) => Ok(to),
+            (_, from) => Err(GameError::IllegalTransition { from, to }),
         }
     }
+
+    #[test]
+    fn test_every_transition() {
+        for status in Status::ALL {
+            // only a finished round cares whether it was the last
+            let lasts: &[bool] = match status {
+                Status::RoundOver => &[false, true],
+                _ => &[false],
+            };
+            for &last in lasts {
+                let rounds = match (status, last) {
+                    (_, true) => 2,
+                    (Status::Init, false) => 0,
+                    (_, false) => 1,
+                };
+                for (name, action) in ACTIONS {
+                    let mut game = game_in(status, rounds);
+                    let result = action(&mut game).map(|_| game.status);
+                    assert_eq!(
+                        result,
+                        expected(name, status, last),
+                        "{} from {} (last: {})",
+                        name,
+                        status,
+                        last
+                    );
+
+                    let new_rounds = match (name, &result) {
+                        ("start" | "next_round", Ok(_)) => rounds + 1,
+                        _ => rounds,
+                    };
+                    assert_eq!(game.rounds.len(), new_rounds, "{} from {}", name, status);
+                    if result.is_err() {
+                        assert_eq!(game.status, status);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_play_through() {
+        let mut game = Game::with_seed(1, 9);
+        game.opts.num_rounds = 3;
+        game.start().unwrap();
+        for _ in 1..3 {
+            assert!(!game.is_last_round());
+            game.end_round().unwrap();
+            assert_eq!(game.finish(), Err(GameError::RoundsLeft));
+            game.next_round().unwrap();
+        }
+        assert!(game.is_last_round());
+        game.end_round().unwrap();
+        assert_eq!(game.next_round(), Err(GameError::NoRoundsLeft));
+        game.finish().unwrap();
+        assert_eq!(game.status, Status::GameOver);
+
+        let notes: Vec<Note> = game.rounds.iter().map(|r| r.note_to_guess).collect();
+        assert_eq!(note_strings(&notes), note_strings(&game.note_sequence()));
+    }
+
+    #[test]
+    fn test_no_rounds() {
+        let mut game = Game::with_seed(1, 9);
+        game.opts.num_rounds = 0;
+        assert_eq!(game.start(), Err(GameError::NoRounds));
+        assert_eq!(game.status, Status::Init);
+        assert!(game.rounds.is_empty());
+    }
+
+    fn note(s: &str) -> Note {
+        s.parse().unwrap()
+    }
+
+    fn coord(string: i32, fret: i32) -> FretCoord {
+        FretCoord { string, fret }
+    }
+
+    /// A started game for players 1 and 2 where the note to guess is `target`.
+    fn guessing_game(target: &str, round_end: RoundEnd) -> Game {
+        let mut game = Game::with_seed(1, 11);
+        game.player_ids.push(2);
+        game.opts.round_end = round_end;
+        game.start().unwrap();
+        game.rounds[0].note_to_guess = note(target);
+        game
+    }
+
+    #[test]
+    fn test_octave_rule() {
+        assert!(OctaveRule::Exact.matches(note("C#4"), note("Db4")));
+        assert!(!OctaveRule::Exact.matches(note("C#4"), note("C#5")));
+        assert!(OctaveRule::Any.matches(note("B#3"), note("C5")));
+        assert!(!OctaveRule::Any.matches(note("C4"), note("B4")));
+    }
+
+    #[test]
+    fn test_submit_guess() {
+        // E4 is the open high E string or the 5th fret of the B string
+        let mut game = guessing_game("E4", RoundEnd::FirstCorrect);
+        assert_eq!(
+            game.submit_guess(3, coord(1, 0)),
+            Err(GameError::NotAPlayer(3))
+        );
+        assert_eq!(
+            game.submit_guess(1, coord(1, 5)),
+            Err(GameError::OffTheBoard(coord(1, 5)))
+        );
+        // E3 is the wrong octave
+        assert_eq!(game.submit_guess(1, coord(4, 2)), Ok(false));
+        assert_eq!(game.status, Status::Playing);
+        assert_eq!(game.submit_guess(2, coord(1, 0)), Ok(true));
+        assert_eq!(game.status, Status::RoundOver);
+        assert_eq!(
+            game.submit_guess(1, coord(1, 0)),
+            Err(GameError::NotPlaying(Status::RoundOver))
+        );
+
+        let guesses = &game.rounds[0].guesses;
+        assert_eq!(guesses.len(), 2);
+        assert_eq!(guesses[1].user_id, Some(2));
+        assert_eq!(guesses[1].clicked_fret, coord(1, 0));
+        assert!(guesses[1].is_correct);
+
+        let mut game = guessing_game("E4", RoundEnd::FirstCorrect);
+        game.opts.octave_rule = OctaveRule::Any;
+        assert_eq!(game.submit_guess(1, coord(4, 2)), Ok(true));
+    }
+
+    #[test]
+    fn test_submit_note_guess() {
+        let e4 = note("E4");
+        let mut game = guessing_game("E4", RoundEnd::AllCorrect);
+        game.opts.end_fret = 12;
+        game.status = Status::Init;
+        assert_eq!(
+            game.submit_note_guess(1, e4, None),
+            Err(GameError::NotPlaying(Status::Init))
+        );
+        game.status = Status::Playing;
+        assert_eq!(
+            game.submit_note_guess(3, e4, None),
+            Err(GameError::NotAPlayer(3))
+        );
+        assert_eq!(
+            game.submit_note_guess(1, e4, Some(6)),
+            Err(GameError::NotOnTheBoard {
+                midi: 64,
+                string: Some(6)
+            })
+        );
+        assert_eq!(game.submit_note_guess(1, note("E3"), None), Ok(false));
+        assert_eq!(game.submit_note_guess(1, e4, Some(2)), Ok(true));
+        assert_eq!(game.submit_note_guess(2, e4, None), Ok(true));
+        assert_eq!(game.status, Status::RoundOver);
+        assert_eq!(
+            game.submit_note_guess(2, e4, None),
+            Err(GameError::NotPlaying(Status::RoundOver))
+        );
+
+        // recorded where they were played, and scored like clicks
+        let guesses = &game.rounds[0].guesses;
+        assert_eq!(guesses.len(), 3);
+        assert_eq!(guesses[1].clicked_fret, coord(2, 5));
+        assert_eq!(guesses[2].clicked_fret, coord(1, 0));
+        assert_eq!(game.standings()[0].correct, 1);
+    }
+
+    #[test]
+    fn test_round_end_rules() {
+        let mut game = guessing_game("E4", RoundEnd::AllCorrect);
+        assert_eq!(game.submit_guess(1, coord(1, 0)), Ok(true));
+        assert_eq!(
+            game.submit_guess(1, coord(1, 0)),
+            Err(GameError::AlreadyGuessed(1))
+        );
+        assert_eq!(game.submit_guess(2, coord(1, 1)), Ok(false));
+        assert_eq!(game.status, Status::Playing);
+        assert_eq!(game.submit_guess(2, coord(1, 0)), Ok(true));
+        assert_eq!(game.status, Status::RoundOver);
+
+        let mut game = guessing_game("E4", RoundEnd::AllGuessed);
+        assert_eq!(game.submit_guess(1, coord(1, 1)), Ok(false));
+        assert_eq!(
+            game.submit_guess(1, coord(1, 0)),
+            Err(GameError::AlreadyGuessed(1))
+        );
+        assert_eq!(game.status, Status::Playing);
+        assert_eq!(game.submit_guess(2, coord(1, 2)), Ok(false));
+        assert_eq!(game.status, Status::RoundOver);
+    }
+
+    #[test]
+    fn test_join() {
+        let mut game = Game::with_seed(1, 0);
+        game.opts.max_players = 3;
+        assert_eq!(game.join(1), Err(GameError::AlreadyJoined(1)));
+        game.join(2).unwrap();
+        game.join(3).unwrap();
+        assert_eq!(game.join(4), Err(GameError::GameFull));
+        assert_eq!(game.player_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_late_join() {
+        use Status::*;
+        for late_join in LateJoin::ALL {
+            for status in Status::ALL {
+                let mut game = Game::with_seed(1, 0);
+                game.opts.late_join = late_join;
+                game.status = status;
+
+                let open = matches!(
+                    (status, late_join),
+                    (Init, _)
+                        | (Playing, LateJoin::Anytime)
+                        | (RoundOver, LateJoin::BetweenRounds | LateJoin::Anytime)
+                );
+                let expected = if open {
+                    Ok(())
+                } else {
+                    Err(GameError::JoinClosed(status))
+                };
+                assert_eq!(game.join(2), expected, "{} {}", late_join, status);
+            }
+        }
+    }
+
+    #[test]
+    fn test_leave() {
+        let mut game = guessing_game("E4", RoundEnd::AllCorrect);
+        assert_eq!(game.join(3), Err(GameError::JoinClosed(Status::Playing)));
+        game.opts.late_join = LateJoin::Anytime;
+        game.join(3).unwrap();
+        assert_eq!(game.leave(4), Err(GameError::NotAPlayer(4)));
+
+        // the host leaves and player 2 takes over
+        game.leave(1).unwrap();
+        assert_eq!(game.host_id, Some(2));
+        assert_eq!(game.submit_guess(2, coord(1, 0)), Ok(true));
+        assert_eq!(game.status, Status::Playing);
+
+        // the round was only waiting on player 3
+        game.leave(3).unwrap();
+        assert_eq!(game.status, Status::RoundOver);
+
+        game.leave(2).unwrap();
+        assert_eq!(game.status, Status::NoPlayers);
+        assert_eq!(game.host_id, Some(2));
+        assert_eq!(game.leave(2), Err(GameError::JoinClosed(Status::NoPlayers)));
+    }
 }
@@ -0,0 +1,189 @@
+use crate::game::{Game, Guess, Round};
+use crate::user::UserId;
+use serde::Serialize;
+
+/// Points for finding the note, before any bonus.
+pub const CORRECT_POINTS: i32 = 100;
+/// Extra points for an instant answer, shrinking to nothing at
+/// `SPEED_BONUS_MS`.
+pub const SPEED_BONUS: i32 = 100;
+pub const SPEED_BONUS_MS: i64 = 10_000;
+/// Each round found in a row after the first adds this to the multiplier, up
+/// to `MAX_STREAK_MULTIPLIER`.
+pub const STREAK_STEP: f64 = 0.25;
+pub const MAX_STREAK_MULTIPLIER: f64 = 2.0;
+/// Points lost for each wrong click.
+pub const WRONG_PENALTY: i32 = 25;
+
+/// A player's score and how they got it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Standing {
+    pub user_id: UserId,
+    pub points: i32,
+    pub correct: u32,
+    pub wrong: u32,
+    /// Most rounds in a row the player found the note in.
+    pub best_streak: u32,
+}
+
+impl Standing {
+    fn new(user_id: UserId) -> Standing {
+        Standing {
+            user_id,
+            points: 0,
+            correct: 0,
+            wrong: 0,
+            best_streak: 0,
+        }
+    }
+}
+
+/// Multiplier for the `streak`th round found in a row.
+pub fn streak_multiplier(streak: u32) -> f64 {
+    (1.0 + streak.saturating_sub(1) as f64 * STREAK_STEP).min(MAX_STREAK_MULTIPLIER)
+}
+
+/// Points for finding the note `response_ms` after the round started, on a
+/// streak of `streak` rounds including this one. Guesses with no known
+/// response time get no speed bonus.
+pub fn correct_points(response_ms: Option<i64>, streak: u32) -> i32 {
+    let speed_bonus = response_ms.map_or(0, |ms| {
+        let left = (SPEED_BONUS_MS - ms.max(0)).max(0);
+        (SPEED_BONUS as i64 * left / SPEED_BONUS_MS) as i32
+    });
+
+    ((CORRECT_POINTS + speed_bonus) as f64 * streak_multiplier(streak)).round() as i32
+}
+
+fn response_ms(round: &Round, guess: &Guess) -> Option<i64> {
+    Some(guess.at? - round.started_at?)
+}
+
+/// Scores everyone in the game from its rounds so far, leader first: the
+/// current players, and anyone who left after guessing. Ties go to whoever
+/// found more notes, then to the lower user id.
+pub fn standings(game: &Game) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = game
+        .player_ids
+        .iter()
+        .map(|&id| Standing::new(id))
+        .collect();
+    let guessers = game
+        .rounds
+        .iter()
+        .flat_map(|round| round.guesses.iter().filter_map(|g| g.user_id));
+    for user_id in guessers {
+        if !standings.iter().any(|s| s.user_id == user_id) {
+            standings.push(Standing::new(user_id));
+        }
+    }
+    let mut streaks: Vec<u32> = vec![0; standings.len()];
+
+    for round in &game.rounds {
+        let mut found = vec![false; standings.len()];
+
+        for guess in &round.guesses {
+            let Some(i) = guess
+                .user_id
+                .and_then(|id| standings.iter().position(|s| s.user_id == id))
+            else {
+                continue;
+            };
+            let standing = &mut standings[i];
+
+            if guess.is_correct {
+                streaks[i] += 1;
+                found[i] = true;
+                standing.correct += 1;
+                standing.points += correct_points(response_ms(round, guess), streaks[i]);
+                standing.best_streak = standing.best_streak.max(streaks[i]);
+            } else {
+                standing.wrong += 1;
+                standing.points -= WRONG_PENALTY;
+            }
+        }
+
+        for (streak, found) in streaks.iter_mut().zip(found) {
+            if !found {
+                *streak = 0;
+            }
+        }
+    }
+
+    standings.sort_by(|a, b| {
+        b.points
+            .cmp(&a.points)
+            .then(b.correct.cmp(&a.correct))
+            .then(a.user_id.cmp(&b.user_id))
+    });
+    standings
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game::score::*;
+    use crate::game::Status;
+    use crate::theory::{FretCoord, Note};
+
+    fn guess(user_id: UserId, is_correct: bool, at: Option<i64>) -> Guess {
+        Guess {
+            user_id: Some(user_id),
+            clicked_fret: FretCoord { string: 1, fret: 0 },
+            is_correct,
+            at,
+        }
+    }
+
+    fn round(guesses: Vec<Guess>) -> Round {
+        Round {
+            note_to_guess: Note::from_midi(64),
+            guesses,
+            started_at: Some(1_000),
+        }
+    }
+
+    #[test]
+    fn test_correct_points() {
+        assert_eq!(correct_points(Some(0), 1), 200);
+        assert_eq!(correct_points(Some(5_000), 1), 150);
+        assert_eq!(correct_points(Some(60_000), 1), 100);
+        assert_eq!(correct_points(None, 1), 100);
+        assert_eq!(correct_points(Some(5_000), 3), 225);
+        assert_eq!(correct_points(None, 10), 200);
+    }
+
+    #[test]
+    fn test_standings() {
+        let mut game = Game::with_seed(1, 0);
+        game.player_ids.extend([2, 3]);
+        game.rounds = vec![
+            round(vec![
+                guess(2, false, Some(2_000)),
+                guess(1, true, Some(3_000)),
+            ]),
+            round(vec![guess(1, true, Some(6_000))]),
+            // player 1's streak ends
+            round(vec![guess(2, true, Some(1_000))]),
+        ];
+
+        let standings = game.standings();
+        assert_eq!(
+            standings.iter().map(|s| s.user_id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        // 180 + 150 * 1.25
+        assert_eq!(standings[0].points, 180 + 188);
+        assert_eq!((standings[0].correct, standings[0].best_streak), (2, 2));
+        assert_eq!(standings[1].points, 200 - WRONG_PENALTY);
+        assert_eq!((standings[1].correct, standings[1].wrong), (1, 1));
+        assert_eq!(standings[2], Standing::new(3));
+
+        assert_eq!(game.final_standings(), None);
+        game.status = Status::GameOver;
+        assert_eq!(game.final_standings(), Some(standings.clone()));
+
+        // leaving doesn't take a player's points with them
+        game.player_ids.retain(|&id| id != 1);
+        assert_eq!(game.standings(), standings);
+    }
+}
@@ -4,8 +4,11 @@ use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::{Error, Pool, Sqlite, SqlitePool};
 
 pub mod app_state;
+pub mod audio;
 pub mod auth;
 pub mod game;
+pub mod midi;
+pub mod render;
 pub mod routes;
 pub mod theory;
 pub mod user;
@@ -1,6 +1,6 @@
 use axum::routing::{get, post};
 use axum::Router;
-use fq::{auth, routes, ws};
+use fq::{auth, game, routes, ws};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
@@ -35,7 +35,10 @@ async fn main() {
     let app_state = Arc::new(AppState {
         pool,
         rooms: Mutex::new(HashMap::new()),
+        note_wavs: Mutex::new(HashMap::new()),
     });
+    // games that were running when the server stopped carry on
+    game::clock::resume(&app_state).await.unwrap();
 
     let router = Router::new()
         .route("/", get(routes::index_page))
@@ -45,6 +48,11 @@ async fn main() {
         .route("/user/name", post(routes::update_username))
         .route("/games", post(routes::handle_game_create))
         .route("/games/:id", get(routes::game_page))
+        .route("/games/:id/export.mid", get(routes::game_midi_export))
+        .route("/games/:id/fretboard.svg", get(routes::game_fretboard_svg))
+        .route("/notes/:note", get(routes::note_wav))
+        .route("/render/fretboard.svg", get(routes::fretboard_svg))
+        .route("/render/staff.svg", get(routes::staff_svg))
         // .route("/games/:id/start", post(routes::handle_game_start))
         .nest_service("/assets", assets_service)
         .layer(CookieManagerLayer::new())
@@ -0,0 +1,162 @@
+//! Writing Standard MIDI Files.
+
+/// Resolution of the files written, in ticks per quarter note.
+pub const TICKS_PER_QUARTER: u16 = 480;
+
+/// Microseconds per quarter note at 120 bpm.
+const DEFAULT_TEMPO: u32 = 500_000;
+
+const NOTE_ON: u8 = 0x90;
+const NOTE_OFF: u8 = 0x80;
+
+#[derive(Debug, Default)]
+pub struct Track {
+    pub name: String,
+    /// (absolute tick, message bytes), kept in the order they were added.
+    events: Vec<(u32, Vec<u8>)>,
+}
+
+impl Track {
+    pub fn new(name: &str) -> Track {
+        Track {
+            name: name.to_string(),
+            events: vec![],
+        }
+    }
+
+    /// Adds a note from `start` lasting `len` ticks. Notes outside the midi
+    /// range are dropped.
+    pub fn add_note(&mut self, channel: u8, midi: i32, velocity: u8, start: u32, len: u32) {
+        let Ok(key) = u8::try_from(midi) else {
+            return;
+        };
+        if key > 127 {
+            return;
+        }
+
+        let channel = channel & 0x0f;
+        self.events
+            .push((start, vec![NOTE_ON | channel, key, velocity.min(127)]));
+        self.events
+            .push((start + len, vec![NOTE_OFF | channel, key, 0]));
+    }
+
+    fn to_chunk(&self, tempo: Option<u32>) -> Vec<u8> {
+        let mut data = vec![];
+
+        if let Some(tempo) = tempo {
+            data.extend([0x00, 0xff, 0x51, 0x03]);
+            data.extend(&tempo.to_be_bytes()[1..]);
+        }
+
+        data.push(0x00);
+        data.extend([0xff, 0x03]);
+        write_var_len(&mut data, self.name.len() as u32);
+        data.extend(self.name.as_bytes());
+
+        // stable sort, and note offs before note ons at the same tick so that
+        // repeated notes don't cut each other off
+        let mut events: Vec<&(u32, Vec<u8>)> = self.events.iter().collect();
+        events.sort_by_key(|(tick, msg)| (*tick, msg[0] & 0xf0 != NOTE_OFF));
+
+        let mut last_tick = 0;
+        for (tick, msg) in events {
+            write_var_len(&mut data, tick - last_tick);
+            data.extend(msg);
+            last_tick = *tick;
+        }
+
+        data.extend([0x00, 0xff, 0x2f, 0x00]);
+
+        let mut chunk = b"MTrk".to_vec();
+        chunk.extend((data.len() as u32).to_be_bytes());
+        chunk.extend(data);
+        chunk
+    }
+}
+
+/// Writes a format 1 file, one track per `Track`, at 120 bpm.
+pub fn write_smf(tracks: &[Track]) -> Vec<u8> {
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend(6u32.to_be_bytes());
+    bytes.extend(1u16.to_be_bytes());
+    bytes.extend((tracks.len() as u16).to_be_bytes());
+    bytes.extend(TICKS_PER_QUARTER.to_be_bytes());
+
+    for (i, track) in tracks.iter().enumerate() {
+        // the tempo goes in the first track of a format 1 file
+        let tempo = (i == 0).then_some(DEFAULT_TEMPO);
+        bytes.extend(track.to_chunk(tempo));
+    }
+
+    bytes
+}
+
+/// Writes `value` as a MIDI variable-length quantity: 7 bits per byte, most
+/// significant first, with the high bit set on all but the last byte.
+fn write_var_len(out: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+#[cfg(test)]
+mod test {
+    use crate::midi::*;
+
+    #[test]
+    fn test_var_len() {
+        let cases: [(u32, &[u8]); 6] = [
+            (0, &[0x00]),
+            (0x40, &[0x40]),
+            (0x7f, &[0x7f]),
+            (0x80, &[0x81, 0x00]),
+            (0x2000, &[0xc0, 0x00]),
+            (0x0fff_ffff, &[0xff, 0xff, 0xff, 0x7f]),
+        ];
+
+        for (value, expected) in cases {
+            let mut out = vec![];
+            write_var_len(&mut out, value);
+            assert_eq!(out, expected, "{:#x}", value);
+        }
+    }
+
+    #[test]
+    fn test_write_smf() {
+        let mut track = Track::new("notes");
+        track.add_note(0, 60, 100, 0, 480);
+        track.add_note(0, 60, 100, 480, 480);
+        track.add_note(0, 200, 100, 0, 480);
+
+        let bytes = write_smf(&[track, Track::new("empty")]);
+
+        assert_eq!(&bytes[..4], b"MThd");
+        assert_eq!(&bytes[8..14], &[0, 1, 0, 2, 0x01, 0xe0]);
+        assert_eq!(&bytes[14..18], b"MTrk");
+
+        let len = u32::from_be_bytes(bytes[18..22].try_into().unwrap()) as usize;
+        let data = &bytes[22..22 + len];
+        let expected: &[u8] = &[
+            0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, // tempo
+            0x00, 0xff, 0x03, 0x05, b'n', b'o', b't', b'e', b's', // name
+            0x00, 0x90, 60, 100, // on
+            0x83, 0x60, 0x80, 60, 0, // off after 480 ticks
+            0x00, 0x90, 60, 100, // on again
+            0x83, 0x60, 0x80, 60, 0, // off
+            0x00, 0xff, 0x2f, 0x00, // end of track
+        ];
+        assert_eq!(data, expected);
+
+        let second = &bytes[22 + len..];
+        assert_eq!(&second[..4], b"MTrk");
+        assert_eq!(
+            &second[8..],
+            &[0x00, 0xff, 0x03, 0x05, b'e', b'm', b'p', b't', b'y', 0x00, 0xff, 0x2f, 0x00]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use crate::render::Line;
+use crate::theory::{Capo, FretCoord, Fretboard};
+use askama_axum::Template;
+
+pub const DEFAULT_WIDTH: f64 = 200.0;
+pub const DEFAULT_HEIGHT: f64 = 300.0;
+pub const DEFAULT_DOT_COLOR: &str = "white";
+
+/// A highlighted position, drawn as a circle filled with `color`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dot {
+    pub coord: FretCoord,
+    pub color: String,
+}
+
+impl Dot {
+    pub fn new(coord: FretCoord) -> Dot {
+        Dot {
+            coord,
+            color: String::from(DEFAULT_DOT_COLOR),
+        }
+    }
+}
+
+/// Where everything on a fretboard diagram goes. This is the same geometry
+/// `assets/fretboard.js` draws with, so the two must be changed together.
+#[derive(Clone, Debug)]
+pub struct Geometry {
+    width: f64,
+    height: f64,
+    num_strings: i32,
+    start_fret: i32,
+    num_frets: i32,
+    x_margin: f64,
+    y_margin: f64,
+    neck_height: f64,
+    fret_height: f64,
+    string_margin: f64,
+    dot_radius: f64,
+    /// Where each string's own nut is, indexed by string - 1.
+    nut_frets: Vec<i32>,
+}
+
+impl Geometry {
+    pub fn new(fretboard: &Fretboard, width: f64, height: f64) -> Geometry {
+        let num_strings = fretboard.num_strings();
+        let x_margin = width / num_strings as f64;
+        let y_margin = height / 8.0;
+        let neck_width = width - (x_margin * 2.0);
+        let neck_height = height - (y_margin * 2.0);
+
+        // every fret gets a space on the neck except the open strings, which
+        // sit above the nut
+        let (start_fret, end_fret) = fretboard.fret_span();
+        let offset = if start_fret == 0 { 0 } else { 1 };
+        let num_frets = (end_fret - start_fret + offset).max(1);
+        let fret_height = neck_height / num_frets as f64;
+
+        let string_margin = if num_strings > 1 {
+            neck_width / (num_strings - 1) as f64
+        } else {
+            0.0
+        };
+
+        Geometry {
+            width,
+            height,
+            num_strings,
+            start_fret: start_fret - offset,
+            num_frets,
+            x_margin,
+            y_margin,
+            neck_height,
+            fret_height,
+            string_margin,
+            dot_radius: fret_height / 6.0,
+            nut_frets: (1..=num_strings)
+                .map(|s| fretboard.string_spec(s).map_or(0, |spec| spec.nut_fret))
+                .collect(),
+        }
+    }
+
+    /// Number of fret spaces drawn.
+    pub fn num_frets(&self) -> i32 {
+        self.num_frets
+    }
+
+    /// Vertical lines for the strings, lowest string on the left. Short
+    /// strings start at their own nut.
+    pub fn strings(&self) -> Vec<Line> {
+        (0..self.num_strings)
+            .map(|i| {
+                let x = (i as f64 * self.string_margin) + self.x_margin;
+                let nut_fret = self.nut_frets[(self.num_strings - i - 1) as usize];
+                let nut_row = (nut_fret - self.start_fret).clamp(0, self.num_frets);
+                Line {
+                    x1: x,
+                    y1: (nut_row as f64 * self.fret_height) + self.y_margin,
+                    x2: x,
+                    y2: self.y_margin + self.neck_height,
+                }
+            })
+            .collect()
+    }
+
+    /// Horizontal lines for the frets, the nut or lowest fret on top.
+    pub fn frets(&self) -> Vec<Line> {
+        (0..=self.num_frets())
+            .map(|i| {
+                let y = (i as f64 * self.fret_height) + self.y_margin;
+                Line {
+                    x1: self.x_margin,
+                    y1: y,
+                    x2: self.width - self.x_margin,
+                    y2: y,
+                }
+            })
+            .collect()
+    }
+
+    /// Center of the dot for `coord`, halfway between its fret and the one
+    /// below it.
+    pub fn fret_coord(&self, coord: &FretCoord) -> (f64, f64) {
+        let string_offset = (coord.string - self.num_strings).abs();
+        let row = coord.fret - self.start_fret;
+
+        let x = (string_offset as f64 * self.string_margin) + self.x_margin;
+        let mut y = ((row as f64 * self.fret_height) - (self.fret_height / 2.0)) + self.y_margin;
+
+        // place open string dots closer to the top of the fretboard
+        if coord.fret == 0 {
+            y += self.fret_height / 5.0;
+        }
+
+        (x, y)
+    }
+
+    /// A bar across the strings the capo covers, in its fret's space.
+    pub fn capo(&self, capo: &Capo) -> Option<Line> {
+        let xs = (1..=self.num_strings)
+            .filter(|&string| capo.covers(string))
+            .map(|string| {
+                self.fret_coord(&FretCoord {
+                    string,
+                    fret: capo.fret,
+                })
+            });
+        let (_, y) = xs.clone().next()?;
+        let x1 = xs.clone().map(|(x, _)| x).fold(f64::INFINITY, f64::min);
+        let x2 = xs.map(|(x, _)| x).fold(f64::NEG_INFINITY, f64::max);
+
+        Some(Line {
+            x1,
+            y1: y,
+            x2,
+            y2: y,
+        })
+    }
+
+    /// Thickness of the capo bar.
+    pub fn capo_width(&self) -> f64 {
+        self.dot_radius * 1.5
+    }
+
+    pub fn dot(&self, dot: &Dot) -> Circle {
+        let (cx, cy) = self.fret_coord(&dot.coord);
+
+        let mut r = self.dot_radius;
+        if dot.coord.fret == 0 {
+            r -= r / 4.0;
+        }
+
+        Circle {
+            cx,
+            cy,
+            r,
+            fill: dot.color.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circle {
+    pub cx: f64,
+    pub cy: f64,
+    pub r: f64,
+    pub fill: String,
+}
+
+/// A fretboard diagram as a standalone SVG document.
+#[derive(Template)]
+#[template(path = "fretboard.svg", escape = "html")]
+pub struct FretboardSvg {
+    width: f64,
+    height: f64,
+    lines: Vec<Line>,
+    capo: Option<Line>,
+    capo_width: f64,
+    circles: Vec<Circle>,
+}
+
+impl FretboardSvg {
+    /// Draws the fretboard with `dots` on top. Dots that aren't on the
+    /// fretboard are left out.
+    pub fn new(fretboard: &Fretboard, width: f64, height: f64, dots: &[Dot]) -> FretboardSvg {
+        let geometry = Geometry::new(fretboard, width, height);
+
+        let mut lines = geometry.strings();
+        lines.extend(geometry.frets());
+
+        let circles = dots
+            .iter()
+            .filter(|dot| fretboard.note_at(&dot.coord).is_some())
+            .map(|dot| geometry.dot(dot))
+            .collect();
+
+        FretboardSvg {
+            width,
+            height,
+            lines,
+            capo: fretboard.capo().and_then(|capo| geometry.capo(capo)),
+            capo_width: geometry.capo_width(),
+            circles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::fretboard::*;
+    use crate::theory::TuningPreset;
+
+    fn standard_fretboard(start_fret: i32, end_fret: i32) -> Fretboard {
+        Fretboard::new(TuningPreset::Standard.tuning(), start_fret, end_fret)
+    }
+
+    #[test]
+    fn test_geometry_matches_client() {
+        // numbers from fretboard.js with its default options
+        let geometry = Geometry::new(&standard_fretboard(0, 4), DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        assert_eq!(geometry.num_frets(), 4);
+
+        let strings = geometry.strings();
+        assert_eq!(strings.len(), 6);
+        assert_eq!(strings[0].x1, 200.0 / 6.0);
+        assert_eq!(strings[0].y1, 37.5);
+        assert_eq!(strings[0].y2, 262.5);
+
+        let frets = geometry.frets();
+        assert_eq!(frets.len(), 5);
+        assert_eq!(frets[1].y1, 37.5 + 56.25);
+
+        let open_low_e = geometry.dot(&Dot::new(FretCoord { string: 6, fret: 0 }));
+        assert_eq!((open_low_e.cx, open_low_e.cy), (200.0 / 6.0, 20.625));
+        assert_eq!(open_low_e.r, 9.375 * 0.75);
+
+        let (x, y) = geometry.fret_coord(&FretCoord { string: 1, fret: 2 });
+        assert!((x - (200.0 - 200.0 / 6.0)).abs() < 1e-9);
+        assert_eq!(y, 37.5 + 56.25 * 1.5);
+    }
+
+    #[test]
+    fn test_geometry_up_the_neck() {
+        // frets 5 to 7 get a space each, with fret 5 at the top
+        let geometry = Geometry::new(&standard_fretboard(5, 7), DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        assert_eq!(geometry.num_frets(), 3);
+
+        let (_, y) = geometry.fret_coord(&FretCoord { string: 3, fret: 5 });
+        assert_eq!(y, 37.5 + 75.0 / 2.0);
+    }
+
+    #[test]
+    fn test_geometry_with_capo() {
+        // capo at 3 with frets 0 to 2 above it: frets 3 to 5, capo on top
+        let fb = standard_fretboard(0, 2).with_capo(Some(Capo::full(3)));
+        let geometry = Geometry::new(&fb, DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        assert_eq!(geometry.num_frets(), 3);
+
+        let capo = geometry.capo(fb.capo().unwrap()).unwrap();
+        assert_eq!((capo.x1, capo.x2), (200.0 / 6.0, 200.0 - 200.0 / 6.0));
+        assert_eq!(capo.y1, 37.5 + 75.0 / 2.0);
+
+        // partial capo over strings 3 to 5 leaves the nut on top
+        let fb = standard_fretboard(0, 3).with_capo(Some(Capo::partial(2, vec![3, 4, 5])));
+        let geometry = Geometry::new(&fb, DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        assert_eq!(geometry.num_frets(), 5);
+
+        let capo = geometry.capo(fb.capo().unwrap()).unwrap();
+        let (x5, _) = geometry.fret_coord(&FretCoord { string: 5, fret: 2 });
+        let (x3, _) = geometry.fret_coord(&FretCoord { string: 3, fret: 2 });
+        assert_eq!((capo.x1, capo.x2), (x5, x3));
+    }
+
+    #[test]
+    fn test_geometry_short_string() {
+        let banjo = TuningPreset::Banjo;
+        let fb = Fretboard::new(banjo.tuning(), 0, 7).with_strings(banjo.string_specs());
+        let geometry = Geometry::new(&fb, DEFAULT_WIDTH, DEFAULT_HEIGHT);
+
+        let strings = geometry.strings();
+        let fret_height = 225.0 / 7.0;
+        assert_eq!(strings[0].y1, 37.5 + 5.0 * fret_height);
+        assert_eq!(strings[1].y1, 37.5);
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let dots = [
+            Dot::new(FretCoord { string: 6, fret: 0 }),
+            Dot {
+                coord: FretCoord { string: 2, fret: 1 },
+                color: String::from("red\"/>"),
+            },
+            // off the board
+            Dot::new(FretCoord { string: 7, fret: 1 }),
+            Dot::new(FretCoord { string: 1, fret: 5 }),
+        ];
+        let svg = FretboardSvg::new(&standard_fretboard(0, 4), 200.0, 300.0, &dots)
+            .render()
+            .unwrap();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"width="200" height="300" viewBox="0 0 200 300""#));
+        assert_eq!(svg.matches("<line ").count(), 6 + 5);
+        assert_eq!(svg.matches("<circle ").count(), 2);
+        assert!(svg.contains(r#"fill="red&quot;/&gt;""#));
+    }
+}
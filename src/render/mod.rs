@@ -0,0 +1,10 @@
+pub mod fretboard;
+pub mod staff;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
@@ -0,0 +1,293 @@
+use crate::render::Line;
+use crate::theory::clef::Clef;
+use crate::theory::key::Key;
+use crate::theory::{Accidental, Note};
+use askama_axum::Template;
+
+/// Same size `game.js` gives the VexFlow staff.
+pub const DEFAULT_WIDTH: f64 = 200.0;
+pub const DEFAULT_HEIGHT: f64 = 130.0;
+
+const LINE_SPACING: f64 = 10.0;
+const X_MARGIN: f64 = 10.0;
+const CLEF_WIDTH: f64 = 35.0;
+const KEY_ACCIDENTAL_WIDTH: f64 = 10.0;
+const ACCIDENTAL_SIZE: f64 = 20.0;
+const NOTE_HEAD_RX: f64 = 6.5;
+const NOTE_HEAD_RY: f64 = 4.5;
+/// How far ledger lines reach past either side of the note head.
+const LEDGER_OVERHANG: f64 = 5.0;
+
+/// Staff step of the top line.
+const TOP_LINE: i32 = 8;
+
+/// A piece of text, centered on `x` and `y`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glyph {
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    pub text: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteHead {
+    pub cx: f64,
+    pub cy: f64,
+    pub rx: f64,
+    pub ry: f64,
+}
+
+fn accidental_glyph(accidental: Accidental) -> &'static str {
+    match accidental {
+        Accidental::DoubleFlat => "\u{1D12B}",
+        Accidental::Flat => "\u{266D}",
+        Accidental::Natural => "\u{266E}",
+        Accidental::Sharp => "\u{266F}",
+        Accidental::DoubleSharp => "\u{1D12A}",
+    }
+}
+
+/// Staff steps of the key signature's accidentals, in the order they're
+/// written. Each one goes on the first line or space of its letter from the
+/// lowest step the clef allows for sharps or flats.
+pub fn signature_steps(clef: Clef, key: &Key) -> Vec<(i32, Accidental)> {
+    key.signature_accidentals()
+        .into_iter()
+        .map(|(white_key, accidental)| {
+            let lowest = match (clef, accidental) {
+                (Clef::Bass | Clef::Bass8vb, Accidental::Sharp) => 1,
+                (Clef::Bass | Clef::Bass8vb, _) => -1,
+                (_, Accidental::Sharp) => 3,
+                (_, _) => 1,
+            };
+            let note = Note {
+                white_key,
+                octave: 4,
+                accidental: None,
+            };
+            let step = (clef.staff_step(&note) - lowest).rem_euclid(7) + lowest;
+
+            (step, accidental)
+        })
+        .collect()
+}
+
+/// Steps that need a ledger line for a note on `step`.
+pub fn ledger_steps(step: i32) -> Vec<i32> {
+    if step < 0 {
+        (step..0).filter(|s| s % 2 == 0).rev().collect()
+    } else {
+        (TOP_LINE + 1..=step).filter(|s| s % 2 == 0).collect()
+    }
+}
+
+/// A five line staff as a standalone SVG document, with a clef, an optional
+/// key signature and an optional whole note.
+#[derive(Template)]
+#[template(path = "staff.svg", escape = "html")]
+pub struct StaffSvg {
+    width: f64,
+    height: f64,
+    lines: Vec<Line>,
+    glyphs: Vec<Glyph>,
+    note_head: Option<NoteHead>,
+}
+
+impl StaffSvg {
+    /// Draws the sounding `note` where `clef` writes it. With a key signature the note
+    /// only gets an accidental when the signature doesn't already imply it.
+    pub fn new(
+        clef: Clef,
+        key: Option<&Key>,
+        note: Option<&Note>,
+        width: f64,
+        height: f64,
+    ) -> StaffSvg {
+        // the middle line sits in the middle of the image
+        let step_y = |step: i32| height / 2.0 - (step - TOP_LINE / 2) as f64 * LINE_SPACING / 2.0;
+        let left = X_MARGIN;
+        let right = width - X_MARGIN;
+
+        let mut lines: Vec<Line> = (0..=TOP_LINE)
+            .step_by(2)
+            .map(|step| Line {
+                x1: left,
+                y1: step_y(step),
+                x2: right,
+                y2: step_y(step),
+            })
+            .collect();
+        for x in [left, right] {
+            lines.push(Line {
+                x1: x,
+                y1: step_y(TOP_LINE),
+                x2: x,
+                y2: step_y(0),
+            });
+        }
+
+        let clef_x = left + CLEF_WIDTH / 2.0;
+        let (mut glyphs, octave_step) = match clef {
+            Clef::Treble | Clef::Treble8vb => (
+                vec![Glyph {
+                    x: clef_x,
+                    y: step_y(3),
+                    size: 6.0 * LINE_SPACING,
+                    text: "\u{1D11E}",
+                }],
+                -4,
+            ),
+            Clef::Bass | Clef::Bass8vb => (
+                vec![Glyph {
+                    x: clef_x,
+                    y: step_y(5),
+                    size: 4.0 * LINE_SPACING,
+                    text: "\u{1D122}",
+                }],
+                -2,
+            ),
+        };
+        if clef.octave_transposition() != 0 {
+            glyphs.push(Glyph {
+                x: clef_x,
+                y: step_y(octave_step),
+                size: LINE_SPACING,
+                text: "8",
+            });
+        }
+
+        let signature = key.map(|k| signature_steps(clef, k)).unwrap_or_default();
+        let signature_x = left + CLEF_WIDTH;
+        for (i, &(step, accidental)) in signature.iter().enumerate() {
+            glyphs.push(Glyph {
+                x: signature_x + (i as f64 + 0.5) * KEY_ACCIDENTAL_WIDTH,
+                y: step_y(step),
+                size: ACCIDENTAL_SIZE,
+                text: accidental_glyph(accidental),
+            });
+        }
+
+        let note_head = note.map(|note| {
+            let notes_left = signature_x + signature.len() as f64 * KEY_ACCIDENTAL_WIDTH;
+            let cx = (notes_left + right) / 2.0;
+            let step = clef.staff_step(note);
+            let cy = step_y(step);
+
+            for ledger in ledger_steps(step) {
+                lines.push(Line {
+                    x1: cx - NOTE_HEAD_RX - LEDGER_OVERHANG,
+                    y1: step_y(ledger),
+                    x2: cx + NOTE_HEAD_RX + LEDGER_OVERHANG,
+                    y2: step_y(ledger),
+                });
+            }
+
+            let accidental = match key {
+                Some(key) => key.displayed_accidental(note),
+                None => note.accidental,
+            };
+            if let Some(accidental) = accidental {
+                glyphs.push(Glyph {
+                    x: cx - NOTE_HEAD_RX - KEY_ACCIDENTAL_WIDTH,
+                    y: cy,
+                    size: ACCIDENTAL_SIZE,
+                    text: accidental_glyph(accidental),
+                });
+            }
+
+            NoteHead {
+                cx,
+                cy,
+                rx: NOTE_HEAD_RX,
+                ry: NOTE_HEAD_RY,
+            }
+        });
+
+        StaffSvg {
+            width,
+            height,
+            lines,
+            glyphs,
+            note_head,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::render::staff::*;
+    use std::str::FromStr;
+
+    fn key(s: &str) -> Key {
+        Key::from_str(s).unwrap()
+    }
+
+    fn note(s: &str) -> Note {
+        Note::from_str(s).unwrap()
+    }
+
+    fn steps(clef: Clef, k: &str) -> Vec<i32> {
+        signature_steps(clef, &key(k))
+            .iter()
+            .map(|&(step, _)| step)
+            .collect()
+    }
+
+    #[test]
+    fn test_signature_steps() {
+        // F5 C5 G5 D5 A4 E5 B4
+        assert_eq!(steps(Clef::Treble, "C#"), vec![8, 5, 9, 6, 3, 7, 4]);
+        // B4 E5 A4 D5 G4 C5 F4
+        assert_eq!(steps(Clef::Treble, "Cb"), vec![4, 7, 3, 6, 2, 5, 1]);
+        assert_eq!(steps(Clef::Treble8vb, "Cb"), steps(Clef::Treble, "Cb"));
+        // F3 C3 G3 D3 A2 E3 B2
+        assert_eq!(steps(Clef::Bass, "C#"), vec![6, 3, 7, 4, 1, 5, 2]);
+        // B2 E3 A2 D3 G2 C3 F2
+        assert_eq!(steps(Clef::Bass, "Cb"), vec![2, 5, 1, 4, 0, 3, -1]);
+        assert_eq!(steps(Clef::Bass8vb, "Cb"), steps(Clef::Bass, "Cb"));
+        assert_eq!(steps(Clef::Bass, "C"), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_ledger_steps() {
+        assert_eq!(ledger_steps(0), Vec::<i32>::new());
+        assert_eq!(ledger_steps(9), Vec::<i32>::new());
+        assert_eq!(ledger_steps(-1), Vec::<i32>::new());
+        assert_eq!(ledger_steps(-2), vec![-2]);
+        assert_eq!(ledger_steps(-7), vec![-2, -4, -6]);
+        assert_eq!(ledger_steps(12), vec![10, 12]);
+    }
+
+    #[test]
+    fn test_render_staff_snapshot() {
+        // middle C in D major: one ledger line and a natural
+        let svg = StaffSvg::new(
+            Clef::Treble,
+            Some(&key("D")),
+            Some(&note("C4")),
+            DEFAULT_WIDTH,
+            DEFAULT_HEIGHT,
+        )
+        .render()
+        .unwrap();
+
+        assert_eq!(svg, SNAPSHOT);
+    }
+
+    const SNAPSHOT: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="130" viewBox="0 0 200 130">
+  <line x1="10" y1="85" x2="190" y2="85" stroke="black" />
+  <line x1="10" y1="75" x2="190" y2="75" stroke="black" />
+  <line x1="10" y1="65" x2="190" y2="65" stroke="black" />
+  <line x1="10" y1="55" x2="190" y2="55" stroke="black" />
+  <line x1="10" y1="45" x2="190" y2="45" stroke="black" />
+  <line x1="10" y1="45" x2="10" y2="85" stroke="black" />
+  <line x1="190" y1="45" x2="190" y2="85" stroke="black" />
+  <line x1="116" y1="95" x2="139" y2="95" stroke="black" />
+  <text x="27.5" y="70" font-size="60" text-anchor="middle" dominant-baseline="central">𝄞</text>
+  <text x="50" y="45" font-size="20" text-anchor="middle" dominant-baseline="central">♯</text>
+  <text x="60" y="60" font-size="20" text-anchor="middle" dominant-baseline="central">♯</text>
+  <text x="111" y="95" font-size="20" text-anchor="middle" dominant-baseline="central">♮</text>
+  <ellipse cx="127.5" cy="95" rx="6.5" ry="4.5" stroke="black" stroke-width="2" fill="none" />
+</svg>"#;
+}
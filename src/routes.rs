@@ -1,19 +1,25 @@
 use crate::app_state::AppState;
+use crate::audio::synth;
+use crate::render::fretboard::{self, Dot, FretboardSvg};
+use crate::render::staff::{self, StaffSvg};
 use crate::{
     auth::{self, Claims},
-    game::{self, Game, GameId},
-    theory,
+    game::{self, score::Standing, Game, GameId, LateJoin, OctaveRule, RoundEnd},
+    theory::{
+        self, clef::Clef, key::Key, Capo, FretCoord, Fretboard, Note, StringSpec, TuningPreset,
+    },
     user::{self, User, UserId},
 };
-use askama_axum::{IntoResponse, Template};
-use axum::extract::{ConnectInfo, Path, State};
-use axum::http::StatusCode;
+use askama_axum::{IntoResponse, Response, Template};
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::{header, StatusCode};
 use axum::response::Redirect;
 use axum::{headers, Form, Json, TypedHeader};
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
 use sqlx::{Error, Pool, Sqlite};
 use std::borrow::Cow;
+use std::fmt;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::ops::ControlFlow;
@@ -24,10 +30,42 @@ use tower_sessions::Session;
 
 #[derive(Template)]
 #[template(path = "index.html")]
-pub struct IndexTemplate;
+pub struct IndexTemplate {
+    tunings: Vec<(String, String)>,
+    keys: Vec<(String, String)>,
+    clefs: Vec<(String, String)>,
+    octave_rules: Vec<(String, String)>,
+    round_ends: Vec<(String, String)>,
+    late_joins: Vec<(String, String)>,
+}
+
+/// Form values and labels for the options of a select.
+fn choices<T: fmt::Debug + fmt::Display>(all: &[T]) -> Vec<(String, String)> {
+    all.iter()
+        .map(|choice| (format!("{:?}", choice), choice.to_string()))
+        .collect()
+}
+
+/// Finds the choice a select's form value stands for.
+fn parse_choice<T: fmt::Debug + Copy>(value: &str, all: &[T]) -> Result<T, StatusCode> {
+    all.iter()
+        .find(|choice| format!("{:?}", choice) == value)
+        .copied()
+        .ok_or(StatusCode::BAD_REQUEST)
+}
 
 pub async fn index_page() -> IndexTemplate {
-    IndexTemplate {}
+    IndexTemplate {
+        tunings: choices(&TuningPreset::ALL),
+        keys: Key::all()
+            .iter()
+            .map(|key| (key.signature(), key.to_string()))
+            .collect(),
+        clefs: choices(&Clef::ALL),
+        octave_rules: choices(&OctaveRule::ALL),
+        round_ends: choices(&RoundEnd::ALL),
+        late_joins: choices(&LateJoin::ALL),
+    }
 }
 
 #[derive(Template)]
@@ -87,18 +125,88 @@ pub struct GameTemplate {
     status: String,
     note: String,
     player_ids: String,
+    string_names: String,
+    key_signature: String,
+    accidental: String,
+    sounding_note: String,
+    clef: String,
+    vexflow_clef: String,
+    clef_annotation: String,
+    /// Frets the board is drawn from and to, counted from the nut.
+    drawn_frets: (i32, i32),
+    capo_fret: String,
+    capo_strings: String,
+    nut_frets: String,
+    standings: Vec<Standing>,
+    game_over: bool,
 }
 
 impl From<Game> for GameTemplate {
     fn from(game: Game) -> Self {
+        let accidental = match (game.opts.key, game.current_note_to_guess()) {
+            (Some(key), Some(note)) => key
+                .displayed_accidental(&note)
+                .map(|a| a.to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        let clef = game.opts.clef;
+        let (vexflow_clef, clef_annotation) = clef.vexflow();
+        let fretboard = game.opts.fretboard();
+        let final_standings = game.final_standings();
+
+        let capo_fret = game.opts.capo.as_ref().map(|capo| capo.fret);
+        let capo_strings = game
+            .opts
+            .capo
+            .as_ref()
+            .and_then(|capo| capo.strings.as_ref())
+            .map(|strings| {
+                strings
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+
         GameTemplate {
             id: game.id.unwrap(),
             status: game.status.to_string(),
             note: game
                 .current_note_to_guess()
-                .map(|n| n.to_string())
+                .map(|n| clef.written(&n).to_string())
                 .unwrap_or_default(),
             player_ids: game.player_ids.iter().map(|id| id.to_string()).collect(),
+            string_names: game
+                .opts
+                .tuning
+                .iter()
+                .map(|n| n.name())
+                .collect::<Vec<_>>()
+                .join(" "),
+            key_signature: game.opts.key.map(|k| k.signature()).unwrap_or_default(),
+            accidental,
+            sounding_note: game
+                .current_note_to_guess()
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            clef: format!("{:?}", clef),
+            vexflow_clef: String::from(vexflow_clef),
+            clef_annotation: String::from(clef_annotation),
+            drawn_frets: fretboard.fret_span(),
+            capo_fret: capo_fret.map(|f| f.to_string()).unwrap_or_default(),
+            capo_strings,
+            nut_frets: (1..=fretboard.num_strings())
+                .map(|s| {
+                    let nut_fret = fretboard.string_spec(s).map_or(0, |spec| spec.nut_fret);
+                    nut_fret.to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            game_over: final_standings.is_some(),
+            standings: final_standings.unwrap_or_else(|| game.standings()),
         }
     }
 }
@@ -113,13 +221,177 @@ pub async fn game_page(
     }
 }
 
+#[derive(Deserialize)]
+pub struct CreateGamePayload {
+    pub tuning: Option<TuningPreset>,
+    pub custom_tuning: Option<String>,
+    pub key: Option<String>,
+    pub chromatic: Option<String>,
+    /// Empty to use the tuning's usual clef.
+    pub clef: Option<String>,
+    pub capo: Option<String>,
+    pub capo_strings: Option<String>,
+    /// Strings to drill, like "4 5 6". Empty for all of them.
+    pub drill_strings: Option<String>,
+    pub octave_rule: Option<String>,
+    pub round_end: Option<String>,
+    /// Blank for rounds without a time limit.
+    pub round_secs: Option<String>,
+    pub break_secs: Option<String>,
+    pub max_players: Option<String>,
+    pub late_join: Option<String>,
+}
+
+/// Reads string numbers separated by spaces or commas, like "4 5 6".
+fn parse_string_list(s: &str, num_strings: i32) -> Result<Vec<i32>, StatusCode> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<i32>()
+                .ok()
+                .filter(|s| (1..=num_strings).contains(s))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(StatusCode::BAD_REQUEST)
+}
+
+/// Reads a capo from the fret it's at and, for a partial capo, the strings it
+/// covers, like "3 4 5". A blank or 0 fret means there's no capo.
+fn parse_capo(
+    fret: Option<&str>,
+    strings: Option<&str>,
+    num_strings: i32,
+) -> Result<Option<Capo>, StatusCode> {
+    let fret = match fret.map(str::trim).filter(|f| !f.is_empty()) {
+        Some(fret) => fret.parse::<i32>().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => return Ok(None),
+    };
+    if fret == 0 {
+        return Ok(None);
+    }
+    if !(1..=MAX_FRET).contains(&fret) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let strings = parse_string_list(strings.unwrap_or_default(), num_strings)?;
+    if strings.is_empty() {
+        Ok(Some(Capo::full(fret)))
+    } else {
+        Ok(Some(Capo::partial(fret, strings)))
+    }
+}
+
+const MAX_ROUND_SECS: u32 = 600;
+const MAX_BREAK_SECS: u32 = 60;
+const MAX_PLAYERS: u32 = 32;
+
+/// Reads a number of seconds up to `max`. Blank means none.
+fn parse_secs(s: &str, max: u32) -> Result<Option<u32>, StatusCode> {
+    match s.trim() {
+        "" => Ok(None),
+        s => s
+            .parse::<u32>()
+            .ok()
+            .filter(|secs| *secs <= max)
+            .map(Some)
+            .ok_or(StatusCode::BAD_REQUEST),
+    }
+}
+
+pub async fn game_midi_export(
+    Path(game_id): Path<GameId>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, StatusCode> {
+    let game = game::db::fetch_game(&state.pool, game_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let disposition = format!("attachment; filename=\"game-{}.mid\"", game_id);
+    let headers = [
+        (header::CONTENT_TYPE, String::from("audio/midi")),
+        (header::CONTENT_DISPOSITION, disposition),
+    ];
+
+    Ok((headers, game::export::game_to_midi(&game)).into_response())
+}
+
 pub async fn handle_game_create(
     cookies: Cookies,
     State(state): State<Arc<AppState>>,
+    Form(payload): Form<CreateGamePayload>,
 ) -> Result<Redirect, StatusCode> {
     match auth::decode_user_cookie(&cookies) {
         Some(user) => {
-            let game = Game::new(user.id);
+            let mut game = Game::new(user.id);
+
+            match payload.custom_tuning.as_deref().map(str::trim) {
+                Some(custom) if !custom.is_empty() => {
+                    game.opts.tuning =
+                        theory::parse_tuning(custom).map_err(|_| StatusCode::BAD_REQUEST)?;
+                }
+                _ => {
+                    if let Some(preset) = payload.tuning {
+                        game.opts.tuning = preset.tuning();
+                        game.opts.strings = preset.string_specs();
+                        game.opts.clef = preset.clef();
+                    }
+                }
+            }
+
+            let num_strings = game.opts.tuning.len() as i32;
+            let drilled = parse_string_list(
+                payload.drill_strings.as_deref().unwrap_or_default(),
+                num_strings,
+            )?;
+            if !drilled.is_empty() {
+                game.opts
+                    .strings
+                    .resize(num_strings as usize, StringSpec::default());
+                for (i, spec) in game.opts.strings.iter_mut().enumerate() {
+                    spec.skipped = !drilled.contains(&(i as i32 + 1));
+                }
+            }
+
+            game.opts.capo = parse_capo(
+                payload.capo.as_deref(),
+                payload.capo_strings.as_deref(),
+                num_strings,
+            )?;
+
+            if let Some(clef) = payload.clef.as_deref().filter(|c| !c.is_empty()) {
+                game.opts.clef = parse_choice(clef, &Clef::ALL)?;
+            }
+            if let Some(rule) = payload.octave_rule.as_deref() {
+                game.opts.octave_rule = parse_choice(rule, &OctaveRule::ALL)?;
+            }
+            if let Some(round_end) = payload.round_end.as_deref() {
+                game.opts.round_end = parse_choice(round_end, &RoundEnd::ALL)?;
+            }
+            if let Some(secs) = payload.round_secs.as_deref() {
+                // 0 is as good as no limit
+                game.opts.round_secs = parse_secs(secs, MAX_ROUND_SECS)?.filter(|&s| s > 0);
+            }
+            if let Some(secs) = payload.break_secs.as_deref() {
+                game.opts.break_secs =
+                    parse_secs(secs, MAX_BREAK_SECS)?.unwrap_or(game.opts.break_secs);
+            }
+            if let Some(max) = payload.max_players.as_deref().map(str::trim) {
+                if !max.is_empty() {
+                    game.opts.max_players = max
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|max| (1..=MAX_PLAYERS).contains(max))
+                        .ok_or(StatusCode::BAD_REQUEST)?;
+                }
+            }
+            if let Some(late_join) = payload.late_join.as_deref() {
+                game.opts.late_join = parse_choice(late_join, &LateJoin::ALL)?;
+            }
+
+            if let Some(key) = payload.key.as_deref().filter(|k| !k.is_empty()) {
+                game.opts.key = Some(key.parse().map_err(|_| StatusCode::BAD_REQUEST)?);
+                game.opts.chromatic = payload.chromatic.is_some();
+            }
 
             if let Ok(game_id) = game::db::insert_game(&state.pool, game).await {
                 let game_url = format!("/games/{}", game_id);
@@ -138,15 +410,203 @@ pub async fn handle_game_start(
     State(state): State<Arc<AppState>>,
 ) {
     if let Some(user) = auth::decode_user_cookie(&cookies) {
-        if let Ok(mut game) = game::db::fetch_game(&state.pool, game_id).await {
-            if let Some(host_id) = game.host_id {
-                if user.id == host_id {
-                    game.start();
-                    if game::db::update_game(&state.pool, game).await.is_ok() {
-                        tracing::debug!("game started: {}", game_id);
-                    }
-                }
+        let room = state.room(game_id);
+        let started = game::db::change_game(&state.pool, &room.lock, game_id, |game| {
+            if game.host_id != Some(user.id) {
+                return Ok(false);
             }
+            game.start()?;
+            Ok(true)
+        })
+        .await;
+        match started {
+            Ok((_, true)) => {
+                tracing::debug!("game started: {}", game_id);
+                game::clock::start(&state.pool, room, game_id);
+            }
+            Ok((_, false)) => {}
+            Err(e) => tracing::error!("can't start game {}: {}", game_id, e),
+        }
+    }
+}
+
+/// Lowest and highest midi numbers that `note_wav` will render, C1 to C8.
+const NOTE_WAV_RANGE: (i32, i32) = (24, 108);
+
+/// Serves a plucked string recording of a note, e.g. `/notes/C%234.wav`.
+pub async fn note_wav(
+    Path(file_name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, StatusCode> {
+    let note = file_name
+        .strip_suffix(".wav")
+        .and_then(|name| name.parse::<Note>().ok())
+        .filter(Note::is_midi)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let midi = note.midi_num();
+    if midi < NOTE_WAV_RANGE.0 || midi > NOTE_WAV_RANGE.1 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let cached = state.note_wavs.lock().unwrap().get(&midi).cloned();
+    let wav = match cached {
+        Some(wav) => wav,
+        None => {
+            let wav = synth::render_note_wav(&note, theory::A4_HZ);
+            state.note_wavs.lock().unwrap().insert(midi, wav.clone());
+            wav
+        }
+    };
+
+    Ok(([(header::CONTENT_TYPE, "audio/wav")], wav).into_response())
+}
+
+/// Largest width or height, in pixels, of a rendered fretboard.
+const MAX_SVG_SIZE: f64 = 2000.0;
+const MAX_FRET: i32 = 24;
+
+fn svg_size(width: f64, height: f64) -> Result<(f64, f64), StatusCode> {
+    let valid = |size: f64| size > 0.0 && size <= MAX_SVG_SIZE;
+    if valid(width) && valid(height) {
+        Ok((width, height))
+    } else {
+        Err(StatusCode::BAD_REQUEST)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FretboardSvgQuery {
+    pub tuning: Option<TuningPreset>,
+    pub custom_tuning: Option<String>,
+    pub start_fret: Option<i32>,
+    pub end_fret: Option<i32>,
+    pub capo: Option<String>,
+    pub capo_strings: Option<String>,
+    /// Comma separated `string:fret` or `string:fret:color`, e.g. "6:3,1:0:red".
+    pub dots: Option<String>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+}
+
+fn parse_dot(s: &str) -> Option<Dot> {
+    let mut parts = s.trim().split(':');
+    let string = parts.next()?.parse().ok()?;
+    let fret = parts.next()?.parse().ok()?;
+    let mut dot = Dot::new(FretCoord { string, fret });
+
+    if let Some(color) = parts.next() {
+        dot.color = String::from(color);
+    }
+
+    parts.next().is_none().then_some(dot)
+}
+
+/// Renders a fretboard diagram, the same one `fretboard.js` draws, e.g.
+/// `/render/fretboard.svg?tuning=DropD&end_fret=5&dots=6:0,4:2`. Frets are
+/// counted from the capo, if there is one, like in `game::Opts`.
+pub async fn fretboard_svg(
+    Query(query): Query<FretboardSvgQuery>,
+) -> Result<FretboardSvg, StatusCode> {
+    let (tuning, strings) = match query.custom_tuning.as_deref().map(str::trim) {
+        Some(custom) if !custom.is_empty() => (
+            theory::parse_tuning(custom).map_err(|_| StatusCode::BAD_REQUEST)?,
+            vec![],
+        ),
+        _ => {
+            let preset = query.tuning.unwrap_or(TuningPreset::Standard);
+            (preset.tuning(), preset.string_specs())
         }
+    };
+    if tuning.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let start_fret = query.start_fret.unwrap_or(0);
+    let end_fret = query.end_fret.unwrap_or(4);
+    if start_fret < 0 || end_fret < start_fret || end_fret > MAX_FRET {
+        return Err(StatusCode::BAD_REQUEST);
     }
+
+    let (width, height) = svg_size(
+        query.width.unwrap_or(fretboard::DEFAULT_WIDTH),
+        query.height.unwrap_or(fretboard::DEFAULT_HEIGHT),
+    )?;
+
+    let dots = match query.dots.as_deref().filter(|d| !d.is_empty()) {
+        Some(dots) => dots
+            .split(',')
+            .map(parse_dot)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(StatusCode::BAD_REQUEST)?,
+        None => vec![],
+    };
+
+    let capo = parse_capo(
+        query.capo.as_deref(),
+        query.capo_strings.as_deref(),
+        tuning.len() as i32,
+    )?;
+    let fretboard = Fretboard::new(tuning, start_fret, end_fret)
+        .with_capo(capo)
+        .with_strings(strings);
+    Ok(FretboardSvg::new(&fretboard, width, height, &dots))
+}
+
+/// Renders the fretboard a game is played on.
+pub async fn game_fretboard_svg(
+    Path(game_id): Path<GameId>,
+    State(state): State<Arc<AppState>>,
+) -> Result<FretboardSvg, StatusCode> {
+    let game = game::db::fetch_game(&state.pool, game_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(FretboardSvg::new(
+        &game.opts.fretboard(),
+        fretboard::DEFAULT_WIDTH,
+        fretboard::DEFAULT_HEIGHT,
+        &[],
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct StaffSvgQuery {
+    pub note: Option<String>,
+    pub clef: Option<Clef>,
+    /// A key signature like "Bb" or "F#m".
+    pub key: Option<String>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+}
+
+/// Renders a staff with an optional note and key signature, e.g.
+/// `/render/staff.svg?note=C%234&clef=Bass&key=A`.
+pub async fn staff_svg(Query(query): Query<StaffSvgQuery>) -> Result<StaffSvg, StatusCode> {
+    let note = match query.note.as_deref().filter(|n| !n.is_empty()) {
+        Some(note) => Some(
+            note.parse::<Note>()
+                .ok()
+                .filter(Note::is_midi)
+                .ok_or(StatusCode::BAD_REQUEST)?,
+        ),
+        None => None,
+    };
+    let key = match query.key.as_deref().filter(|k| !k.is_empty()) {
+        Some(key) => Some(key.parse::<Key>().map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    let (width, height) = svg_size(
+        query.width.unwrap_or(staff::DEFAULT_WIDTH),
+        query.height.unwrap_or(staff::DEFAULT_HEIGHT),
+    )?;
+
+    Ok(StaffSvg::new(
+        query.clef.unwrap_or(Clef::Treble),
+        key.as_ref(),
+        note.as_ref(),
+        width,
+        height,
+    ))
 }
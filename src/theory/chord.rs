@@ -0,0 +1,464 @@
+use crate::theory::interval::{Interval, Quality};
+use crate::theory::{Accidental, FretCoord, Fretboard, Note, WhiteKey};
+use serde::{Deserialize, Serialize};
+use std::{error, fmt, str};
+
+/// Register that parsed chord roots are placed in.
+const DEFAULT_ROOT_OCTAVE: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ChordKind {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Major6,
+    Minor6,
+    SixNine,
+    Dominant7,
+    Major7,
+    Minor7,
+    MinorMajor7,
+    HalfDiminished7,
+    Diminished7,
+    Augmented7,
+    Dominant7Flat9,
+    Dominant7Sharp9,
+    Add9,
+    Dominant9,
+    Major9,
+    Minor9,
+    Dominant11,
+    Minor11,
+    Dominant13,
+    Major13,
+}
+
+impl ChordKind {
+    pub const ALL: [ChordKind; 26] = [
+        Self::Major,
+        Self::Minor,
+        Self::Diminished,
+        Self::Augmented,
+        Self::Sus2,
+        Self::Sus4,
+        Self::Major6,
+        Self::Minor6,
+        Self::SixNine,
+        Self::Dominant7,
+        Self::Major7,
+        Self::Minor7,
+        Self::MinorMajor7,
+        Self::HalfDiminished7,
+        Self::Diminished7,
+        Self::Augmented7,
+        Self::Dominant7Flat9,
+        Self::Dominant7Sharp9,
+        Self::Add9,
+        Self::Dominant9,
+        Self::Major9,
+        Self::Minor9,
+        Self::Dominant11,
+        Self::Minor11,
+        Self::Dominant13,
+        Self::Major13,
+    ];
+
+    /// Suffixes accepted after the root. The first one is used for display.
+    fn symbols(&self) -> &'static [&'static str] {
+        match self {
+            Self::Major => &["", "maj", "M"],
+            Self::Minor => &["m", "min", "-"],
+            Self::Diminished => &["dim", "°"],
+            Self::Augmented => &["aug", "+"],
+            Self::Sus2 => &["sus2"],
+            Self::Sus4 => &["sus4", "sus"],
+            Self::Major6 => &["6"],
+            Self::Minor6 => &["m6", "min6"],
+            Self::SixNine => &["6/9"],
+            Self::Dominant7 => &["7"],
+            Self::Major7 => &["maj7", "M7", "Δ7"],
+            Self::Minor7 => &["m7", "min7", "-7"],
+            Self::MinorMajor7 => &["mMaj7", "m(maj7)", "mM7"],
+            Self::HalfDiminished7 => &["m7b5", "ø7", "ø"],
+            Self::Diminished7 => &["dim7", "°7"],
+            Self::Augmented7 => &["aug7", "7#5", "+7"],
+            Self::Dominant7Flat9 => &["7b9"],
+            Self::Dominant7Sharp9 => &["7#9"],
+            Self::Add9 => &["add9"],
+            Self::Dominant9 => &["9"],
+            Self::Major9 => &["maj9", "M9"],
+            Self::Minor9 => &["m9", "min9"],
+            Self::Dominant11 => &["11"],
+            Self::Minor11 => &["m11", "min11"],
+            Self::Dominant13 => &["13"],
+            Self::Major13 => &["maj13", "M13"],
+        }
+    }
+
+    fn interval_names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Major => &["P1", "M3", "P5"],
+            Self::Minor => &["P1", "m3", "P5"],
+            Self::Diminished => &["P1", "m3", "d5"],
+            Self::Augmented => &["P1", "M3", "A5"],
+            Self::Sus2 => &["P1", "M2", "P5"],
+            Self::Sus4 => &["P1", "P4", "P5"],
+            Self::Major6 => &["P1", "M3", "P5", "M6"],
+            Self::Minor6 => &["P1", "m3", "P5", "M6"],
+            Self::SixNine => &["P1", "M3", "P5", "M6", "M9"],
+            Self::Dominant7 => &["P1", "M3", "P5", "m7"],
+            Self::Major7 => &["P1", "M3", "P5", "M7"],
+            Self::Minor7 => &["P1", "m3", "P5", "m7"],
+            Self::MinorMajor7 => &["P1", "m3", "P5", "M7"],
+            Self::HalfDiminished7 => &["P1", "m3", "d5", "m7"],
+            Self::Diminished7 => &["P1", "m3", "d5", "d7"],
+            Self::Augmented7 => &["P1", "M3", "A5", "m7"],
+            Self::Dominant7Flat9 => &["P1", "M3", "P5", "m7", "m9"],
+            Self::Dominant7Sharp9 => &["P1", "M3", "P5", "m7", "A9"],
+            Self::Add9 => &["P1", "M3", "P5", "M9"],
+            Self::Dominant9 => &["P1", "M3", "P5", "m7", "M9"],
+            Self::Major9 => &["P1", "M3", "P5", "M7", "M9"],
+            Self::Minor9 => &["P1", "m3", "P5", "m7", "M9"],
+            Self::Dominant11 => &["P1", "M3", "P5", "m7", "M9", "P11"],
+            Self::Minor11 => &["P1", "m3", "P5", "m7", "M9", "P11"],
+            // the 11th is conventionally left out of 13th chords
+            Self::Dominant13 => &["P1", "M3", "P5", "m7", "M9", "M13"],
+            Self::Major13 => &["P1", "M3", "P5", "M7", "M9", "M13"],
+        }
+    }
+
+    /// Intervals of each chord tone above the root.
+    pub fn intervals(&self) -> Vec<Interval> {
+        self.interval_names()
+            .iter()
+            .map(|name| name.parse().expect("chord intervals are valid"))
+            .collect()
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        self.symbols()[0]
+    }
+
+    fn from_symbol(symbol: &str) -> Option<ChordKind> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.symbols().contains(&symbol))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Chord {
+    pub root: Note,
+    pub kind: ChordKind,
+    /// Bass note of a slash chord, e.g. the G in C/G.
+    pub bass: Option<Note>,
+}
+
+impl Chord {
+    pub fn new(root: Note, kind: ChordKind) -> Chord {
+        Chord {
+            root,
+            kind,
+            bass: None,
+        }
+    }
+
+    /// Chord tones stacked up from the root, spelled from the chord's
+    /// intervals. A slash chord's bass note comes first, below the root.
+    pub fn notes(&self) -> Vec<Note> {
+        let tones = self
+            .kind
+            .intervals()
+            .into_iter()
+            .filter_map(|interval| self.root.transpose(interval));
+
+        self.bass_below_root().into_iter().chain(tones).collect()
+    }
+
+    fn bass_below_root(&self) -> Option<Note> {
+        let mut bass = Note {
+            octave: self.root.octave,
+            ..self.bass?
+        };
+        while bass.midi_num() >= self.root.midi_num() {
+            bass.octave -= 1;
+        }
+        Some(bass)
+    }
+
+    /// Lists the ways to play the chord on `fretboard` with every chord tone
+    /// present and the root (or slash bass) as the lowest sounding note. The
+    /// fretted notes of a voicing span at most `max_stretch` frets, open
+    /// strings are free. In chords of four or more tones the perfect fifth may
+    /// be left out.
+    pub fn voicings(&self, fretboard: &Fretboard, max_stretch: i32) -> Vec<Voicing> {
+        let pitch_class = |note: &Note| note.midi_num().rem_euclid(12);
+        let intervals = self.kind.intervals();
+        let tones: Vec<(i32, bool)> = intervals
+            .iter()
+            .filter_map(|interval| {
+                let optional = intervals.len() >= 4 && *interval == Interval::P5;
+                let note = self.root.transpose(*interval)?;
+                Some((pitch_class(&note), !optional))
+            })
+            .collect();
+        let lowest = pitch_class(&self.bass.unwrap_or(self.root));
+
+        let mut allowed = tones.iter().map(|(pc, _)| *pc).collect::<Vec<_>>();
+        allowed.push(lowest);
+
+        let choices: Vec<Vec<FretCoord>> = (1..=fretboard.num_strings())
+            .map(|string| {
+                let (low, high) = fretboard.fret_range(string).unwrap_or((0, -1));
+                (low..=high)
+                    .map(|fret| FretCoord { string, fret })
+                    .filter(|coord| {
+                        fretboard
+                            .note_at(coord)
+                            .is_some_and(|n| allowed.contains(&pitch_class(&n)))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut voicings = vec![];
+        let mut current = vec![];
+        collect_voicings(&choices, max_stretch, &mut current, &mut |coords| {
+            let notes: Vec<Note> = coords.iter().filter_map(|c| fretboard.note_at(c)).collect();
+            let bottom = notes.iter().min_by_key(|n| n.midi_num());
+            let sounded: Vec<i32> = notes.iter().map(pitch_class).collect();
+
+            let is_complete = tones
+                .iter()
+                .all(|(pc, required)| !required || sounded.contains(pc));
+            let has_bass = bottom.is_some_and(|n| pitch_class(n) == lowest);
+
+            if is_complete && has_bass && notes.len() >= 3 {
+                voicings.push(Voicing {
+                    coords: coords.to_vec(),
+                });
+            }
+        });
+
+        voicings.sort_by_key(|v| (v.lowest_fret(), v.coords.len()));
+        voicings
+    }
+}
+
+/// Walks every combination of one coord (or a muted string) per string,
+/// pruning combinations whose fretted notes are wider than `max_stretch`.
+fn collect_voicings(
+    choices: &[Vec<FretCoord>],
+    max_stretch: i32,
+    current: &mut Vec<FretCoord>,
+    found: &mut impl FnMut(&[FretCoord]),
+) {
+    let Some((options, rest)) = choices.split_first() else {
+        found(current);
+        return;
+    };
+
+    collect_voicings(rest, max_stretch, current, found);
+
+    for coord in options {
+        current.push(*coord);
+        if fretted_span(current) <= max_stretch {
+            collect_voicings(rest, max_stretch, current, found);
+        }
+        current.pop();
+    }
+}
+
+fn fretted_span(coords: &[FretCoord]) -> i32 {
+    let fretted = coords.iter().map(|c| c.fret).filter(|&f| f > 0);
+    match (fretted.clone().min(), fretted.max()) {
+        (Some(low), Some(high)) => high - low + 1,
+        _ => 0,
+    }
+}
+
+/// One way of playing a chord. Strings without a coord are muted.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Voicing {
+    pub coords: Vec<FretCoord>,
+}
+
+impl Voicing {
+    /// Lowest fretted position, or 0 if every sounded string is open.
+    pub fn lowest_fret(&self) -> i32 {
+        self.coords
+            .iter()
+            .map(|c| c.fret)
+            .filter(|&f| f > 0)
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.root.name(), self.kind.symbol())?;
+        if let Some(bass) = self.bass {
+            write!(f, "/{}", bass.name())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseChordError {
+    Root,
+    Kind,
+    Bass,
+}
+
+impl fmt::Display for ParseChordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Root => write!(f, "invalid chord root"),
+            Self::Kind => write!(f, "unknown chord symbol"),
+            Self::Bass => write!(f, "invalid slash chord bass note"),
+        }
+    }
+}
+
+impl error::Error for ParseChordError {}
+
+/// Splits a leading note name like "F#" off of `s`, placing it in `octave`.
+fn parse_note_name(s: &str, octave: i32) -> Option<(Note, &str)> {
+    let key_len = s.chars().next()?.len_utf8();
+    let white_key = s[..key_len].parse::<WhiteKey>().ok()?;
+
+    let rest = &s[key_len..];
+    let acc_len = rest
+        .find(|c| !matches!(c, '#' | 'b'))
+        .unwrap_or(rest.len())
+        .min(2);
+    let accidental = match &rest[..acc_len] {
+        "" => None,
+        acc => Some(acc.parse::<Accidental>().ok()?),
+    };
+
+    let note = Note {
+        white_key,
+        accidental,
+        octave,
+    };
+    Some((note, &rest[acc_len..]))
+}
+
+/// Parses chord symbols like `Am`, `F#m7b5`, `Bbmaj7` or `C/G`.
+impl str::FromStr for Chord {
+    type Err = ParseChordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (root, suffix) =
+            parse_note_name(s.trim(), DEFAULT_ROOT_OCTAVE).ok_or(ParseChordError::Root)?;
+
+        if let Some(kind) = ChordKind::from_symbol(suffix) {
+            return Ok(Chord::new(root, kind));
+        }
+
+        let (symbol, bass) = suffix.rsplit_once('/').ok_or(ParseChordError::Kind)?;
+        let kind = ChordKind::from_symbol(symbol).ok_or(ParseChordError::Kind)?;
+        let bass = match parse_note_name(bass, DEFAULT_ROOT_OCTAVE) {
+            Some((note, "")) => note,
+            _ => return Err(ParseChordError::Bass),
+        };
+
+        Ok(Chord {
+            root,
+            kind,
+            bass: Some(bass),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::chord::*;
+    use crate::theory::TuningPreset;
+    use std::str::FromStr;
+
+    fn spelling(symbol: &str) -> String {
+        let chord = Chord::from_str(symbol).unwrap();
+        let names: Vec<String> = chord.notes().iter().map(|n| n.name()).collect();
+        names.join(" ")
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        let symbols = [
+            "C", "Am", "F#m7b5", "Bbmaj7", "Ebdim7", "G7", "Dsus4", "C6/9", "E7#9", "Abm9", "C/G",
+            "Am7/G", "F#/A#",
+        ];
+        for symbol in symbols {
+            assert_eq!(Chord::from_str(symbol).unwrap().to_string(), symbol);
+        }
+
+        assert_eq!(Chord::from_str("Cmin7").unwrap().to_string(), "Cm7");
+        assert_eq!(Chord::from_str("Bø").unwrap().to_string(), "Bm7b5");
+        assert_eq!(Chord::from_str("H7").unwrap_err(), ParseChordError::Root);
+        assert_eq!(Chord::from_str("Cxyz").unwrap_err(), ParseChordError::Kind);
+        assert_eq!(Chord::from_str("C/X").unwrap_err(), ParseChordError::Bass);
+    }
+
+    #[test]
+    fn test_chord_spelling() {
+        assert_eq!(spelling("C"), "C E G");
+        assert_eq!(spelling("F#m7b5"), "F# A C E");
+        assert_eq!(spelling("Ebdim7"), "Eb Gb Bbb Dbb");
+        assert_eq!(spelling("Bbmaj7"), "Bb D F A");
+        assert_eq!(spelling("E7#9"), "E G# B D F##");
+        assert_eq!(spelling("Dbaug"), "Db F A");
+        assert_eq!(spelling("G13"), "G B D F A E");
+        assert_eq!(spelling("C/G"), "G C E G");
+
+        let notes = Chord::from_str("Am7/G").unwrap().notes();
+        assert!(notes[0].midi_num() < notes[1].midi_num());
+        assert_eq!(notes[0].name(), "G");
+    }
+
+    #[test]
+    fn test_chord_voicings() {
+        let fb = Fretboard::new(TuningPreset::Standard.tuning(), 0, 5);
+        let c_major = Chord::from_str("C").unwrap();
+        let voicings = c_major.voicings(&fb, 4);
+
+        // the open C shape: x32010
+        let open_c = Voicing {
+            coords: vec![
+                FretCoord { string: 1, fret: 0 },
+                FretCoord { string: 2, fret: 1 },
+                FretCoord { string: 3, fret: 0 },
+                FretCoord { string: 4, fret: 2 },
+                FretCoord { string: 5, fret: 3 },
+            ],
+        };
+        assert!(voicings.contains(&open_c));
+
+        for voicing in &voicings {
+            let notes: Vec<Note> = voicing
+                .coords
+                .iter()
+                .map(|c| fb.note_at(c).unwrap())
+                .collect();
+            let bottom = notes.iter().min_by_key(|n| n.midi_num()).unwrap();
+            assert_eq!(bottom.midi_num().rem_euclid(12), 0);
+            assert!(fretted_span(&voicing.coords) <= 4);
+        }
+
+        let c_over_g = Chord::from_str("C/G").unwrap();
+        let voicings = c_over_g.voicings(&fb, 4);
+        assert!(!voicings.is_empty());
+        assert!(!voicings.contains(&open_c));
+
+        assert!(c_major
+            .voicings(&fb, 1)
+            .iter()
+            .all(|v| fretted_span(&v.coords) <= 1));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::theory::{Note, WhiteKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The clef music is read in, along with how far the instrument sounds from
+/// where it's written. Guitar and bass parts are written an octave above
+/// where they sound to keep them on the staff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Clef {
+    Treble,
+    Bass,
+    /// Treble clef with an 8 below it, sounding an octave lower than written.
+    /// The usual clef for guitar.
+    Treble8vb,
+    /// Bass clef sounding an octave lower than written, the way bass guitar
+    /// parts are read.
+    Bass8vb,
+}
+
+impl Clef {
+    pub const ALL: [Clef; 4] = [Self::Treble8vb, Self::Treble, Self::Bass8vb, Self::Bass];
+
+    /// Octaves the instrument sounds above where it's written, negative when
+    /// it sounds below.
+    pub fn octave_transposition(&self) -> i32 {
+        match self {
+            Self::Treble | Self::Bass => 0,
+            Self::Treble8vb | Self::Bass8vb => -1,
+        }
+    }
+
+    /// The note written on the bottom line of the staff.
+    pub fn bottom_line(&self) -> Note {
+        let (white_key, octave) = match self {
+            Self::Treble | Self::Treble8vb => (WhiteKey::E, 4),
+            Self::Bass | Self::Bass8vb => (WhiteKey::G, 2),
+        };
+
+        Note {
+            white_key,
+            octave,
+            accidental: None,
+        }
+    }
+
+    /// The note written for a sounding note.
+    pub fn written(&self, sounding: &Note) -> Note {
+        Note {
+            octave: sounding.octave - self.octave_transposition(),
+            ..*sounding
+        }
+    }
+
+    /// The note that sounds for a written note.
+    pub fn sounding(&self, written: &Note) -> Note {
+        Note {
+            octave: written.octave + self.octave_transposition(),
+            ..*written
+        }
+    }
+
+    /// Where the sounding `note` is written on the staff, in steps up from the
+    /// bottom line: even steps are lines and odd steps are spaces, so the top
+    /// line is step 8. Accidentals don't move a note.
+    pub fn staff_step(&self, note: &Note) -> i32 {
+        self.written(note).diatonic_num() - self.bottom_line().diatonic_num()
+    }
+
+    /// Clef name and annotation for VexFlow's `addClef`.
+    pub fn vexflow(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Treble => ("treble", ""),
+            Self::Bass => ("bass", ""),
+            Self::Treble8vb => ("treble", "8vb"),
+            Self::Bass8vb => ("bass", "8vb"),
+        }
+    }
+}
+
+impl fmt::Display for Clef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Treble => "Treble",
+                Self::Bass => "Bass",
+                Self::Treble8vb => "Treble 8vb",
+                Self::Bass8vb => "Bass 8vb",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::clef::*;
+    use std::str::FromStr;
+
+    fn note(s: &str) -> Note {
+        Note::from_str(s).unwrap()
+    }
+
+    fn step(clef: Clef, s: &str) -> i32 {
+        clef.staff_step(&note(s))
+    }
+
+    #[test]
+    fn test_staff_steps() {
+        assert_eq!(step(Clef::Treble, "E4"), 0);
+        assert_eq!(step(Clef::Treble, "F5"), 8);
+        assert_eq!(step(Clef::Treble, "C4"), -2);
+        assert_eq!(step(Clef::Treble, "C#4"), -2);
+        assert_eq!(step(Clef::Treble, "Cb4"), -2);
+        assert_eq!(step(Clef::Treble, "B#3"), -3);
+
+        assert_eq!(step(Clef::Bass, "G2"), 0);
+        assert_eq!(step(Clef::Bass, "A3"), 8);
+        assert_eq!(step(Clef::Bass, "C4"), 10);
+        assert_eq!(step(Clef::Bass, "E1"), -9);
+
+        // guitar parts: the low E string sits under three ledger lines
+        assert_eq!(step(Clef::Treble8vb, "E2"), -7);
+        assert_eq!(step(Clef::Treble8vb, "E3"), 0);
+        assert_eq!(step(Clef::Treble8vb, "E4"), 7);
+
+        // and the low E of a bass under one
+        assert_eq!(step(Clef::Bass8vb, "E1"), -2);
+    }
+
+    #[test]
+    fn test_written_and_sounding() {
+        // open high E on guitar sounds E4 and is written E5
+        assert_eq!(Clef::Treble8vb.written(&note("E4")).to_string(), "E/5");
+        assert_eq!(Clef::Treble8vb.sounding(&note("E5")).to_string(), "E/4");
+        assert_eq!(Clef::Bass8vb.written(&note("Bb0")).to_string(), "Bb/1");
+        assert_eq!(Clef::Treble.written(&note("C#4")).to_string(), "C#/4");
+
+        for clef in Clef::ALL {
+            let sounding = note("F#2");
+            assert_eq!(clef.sounding(&clef.written(&sounding)), sounding);
+        }
+    }
+}
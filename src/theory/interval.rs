@@ -0,0 +1,286 @@
+use crate::theory::{Accidental, Note, WhiteKey};
+use serde::{Deserialize, Serialize};
+use std::{error, fmt, str};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Quality {
+    Diminished,
+    Minor,
+    Perfect,
+    Major,
+    Augmented,
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Diminished => "d",
+                Self::Minor => "m",
+                Self::Perfect => "P",
+                Self::Major => "M",
+                Self::Augmented => "A",
+            }
+        )
+    }
+}
+
+/// An ascending interval such as a minor third or a perfect fifth. Numbers
+/// above 8 are compound intervals, e.g. a major ninth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Interval {
+    quality: Quality,
+    number: i32,
+}
+
+impl Interval {
+    pub const P1: Interval = Interval::simple(Quality::Perfect, 1);
+    pub const M2: Interval = Interval::simple(Quality::Major, 2);
+    pub const M3: Interval = Interval::simple(Quality::Major, 3);
+    pub const P4: Interval = Interval::simple(Quality::Perfect, 4);
+    pub const P5: Interval = Interval::simple(Quality::Perfect, 5);
+    pub const M6: Interval = Interval::simple(Quality::Major, 6);
+    pub const M7: Interval = Interval::simple(Quality::Major, 7);
+    pub const P8: Interval = Interval::simple(Quality::Perfect, 8);
+
+    const fn simple(quality: Quality, number: i32) -> Interval {
+        Interval { quality, number }
+    }
+
+    /// Returns `None` for intervals that don't exist, like a major fifth or a
+    /// diminished unison.
+    pub fn new(quality: Quality, number: i32) -> Option<Interval> {
+        let interval = Interval { quality, number };
+        (number >= 1 && interval.quality_offset().is_some()).then_some(interval)
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    pub fn number(&self) -> i32 {
+        self.number
+    }
+
+    pub fn is_compound(&self) -> bool {
+        self.number > 8
+    }
+
+    pub fn semitones(&self) -> i32 {
+        let offset = self.quality_offset().expect("interval quality is valid");
+        major_or_perfect_semitones(self.number) + offset
+    }
+
+    /// Finds the interval spanning `number` letters and `semitones` half steps.
+    fn from_semitones(number: i32, semitones: i32) -> Option<Interval> {
+        if number < 1 {
+            return None;
+        }
+
+        let offset = semitones - major_or_perfect_semitones(number);
+        let quality = if is_perfect_number(number) {
+            match offset {
+                -1 => Quality::Diminished,
+                0 => Quality::Perfect,
+                1 => Quality::Augmented,
+                _ => return None,
+            }
+        } else {
+            match offset {
+                -2 => Quality::Diminished,
+                -1 => Quality::Minor,
+                0 => Quality::Major,
+                1 => Quality::Augmented,
+                _ => return None,
+            }
+        };
+
+        Interval::new(quality, number)
+    }
+
+    fn quality_offset(&self) -> Option<i32> {
+        let offset = if is_perfect_number(self.number) {
+            match self.quality {
+                // a diminished unison would descend
+                Quality::Diminished if self.number == 1 => return None,
+                Quality::Diminished => -1,
+                Quality::Perfect => 0,
+                Quality::Augmented => 1,
+                _ => return None,
+            }
+        } else {
+            match self.quality {
+                Quality::Diminished => -2,
+                Quality::Minor => -1,
+                Quality::Major => 0,
+                Quality::Augmented => 1,
+                _ => return None,
+            }
+        };
+
+        Some(offset)
+    }
+}
+
+fn is_perfect_number(number: i32) -> bool {
+    matches!((number - 1) % 7, 0 | 3 | 4)
+}
+
+fn major_or_perfect_semitones(number: i32) -> i32 {
+    const SIMPLE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+    let steps = number - 1;
+    SIMPLE[steps.rem_euclid(7) as usize] + 12 * steps.div_euclid(7)
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.quality, self.number)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseIntervalError;
+
+impl fmt::Display for ParseIntervalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected an interval like m3, P5, A4 or M9")
+    }
+}
+
+impl error::Error for ParseIntervalError {}
+
+impl str::FromStr for Interval {
+    type Err = ParseIntervalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let quality = match chars.next() {
+            Some('d') => Quality::Diminished,
+            Some('m') => Quality::Minor,
+            Some('P') => Quality::Perfect,
+            Some('M') => Quality::Major,
+            Some('A') => Quality::Augmented,
+            _ => return Err(ParseIntervalError),
+        };
+        let number = chars.as_str().parse().map_err(|_| ParseIntervalError)?;
+
+        Interval::new(quality, number).ok_or(ParseIntervalError)
+    }
+}
+
+impl Note {
+    /// Transposes up by `interval`, keeping the letter spelling implied by the
+    /// interval number, so C + m3 is Eb rather than D#. Returns `None` when the
+    /// result would need more than a double sharp or flat.
+    pub fn transpose(&self, interval: Interval) -> Option<Note> {
+        let diatonic_num = self.diatonic_num() + interval.number - 1;
+        let natural = Note {
+            white_key: WhiteKey::from_index(diatonic_num),
+            octave: diatonic_num.div_euclid(7),
+            accidental: None,
+        };
+
+        let offset = self.midi_num() + interval.semitones() - natural.midi_num();
+        let accidental = match Accidental::from_semitone_offset(offset)? {
+            Accidental::Natural => None,
+            acc => Some(acc),
+        };
+
+        Some(Note {
+            accidental,
+            ..natural
+        })
+    }
+
+    /// Returns the ascending interval from this note up to `other`, or `None`
+    /// if `other` is written lower or the interval has no standard name.
+    pub fn interval_to(&self, other: Note) -> Option<Interval> {
+        let number = other.diatonic_num() - self.diatonic_num() + 1;
+        let semitones = other.midi_num() - self.midi_num();
+        Interval::from_semitones(number, semitones)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::interval::*;
+    use std::str::FromStr;
+
+    fn note(s: &str) -> Note {
+        Note::from_str(s).unwrap()
+    }
+
+    fn interval(s: &str) -> Interval {
+        Interval::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        for s in [
+            "P1", "m2", "M3", "A4", "d5", "P5", "d7", "P8", "M9", "P11", "m13",
+        ] {
+            assert_eq!(interval(s).to_string(), s);
+        }
+
+        for s in ["", "P3", "M5", "d1", "m0", "X4", "m"] {
+            assert_eq!(Interval::from_str(s), Err(ParseIntervalError));
+        }
+
+        assert_eq!(interval("m3").semitones(), 3);
+        assert_eq!(interval("A4").semitones(), 6);
+        assert_eq!(interval("d7").semitones(), 9);
+        assert_eq!(interval("M9").semitones(), 14);
+        assert!(interval("M9").is_compound());
+        assert_eq!(Interval::P8.semitones(), 12);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let cases = [
+            ("C4", "m3", "Eb/4"),
+            ("C4", "A2", "D#/4"),
+            ("E4", "m3", "G/4"),
+            ("B3", "M2", "C#/4"),
+            ("F#4", "A4", "B#/4"),
+            ("Bb3", "d5", "Fb/4"),
+            ("D4", "m7", "C/5"),
+            ("C4", "M10", "E/5"),
+            ("G3", "P8", "G/4"),
+            ("C#4", "d7", "Bb/4"),
+        ];
+
+        for (from, by, to) in cases {
+            let transposed = note(from).transpose(interval(by)).unwrap();
+            assert_eq!(transposed.to_string(), to, "{} + {}", from, by);
+        }
+
+        assert!(note("Fbb4").transpose(interval("d5")).is_none());
+    }
+
+    #[test]
+    fn test_interval_to() {
+        let cases = [
+            ("C4", "Eb4", "m3"),
+            ("C4", "D#4", "A2"),
+            ("C#4", "Db4", "d2"),
+            ("D4", "C5", "m7"),
+            ("C4", "E5", "M10"),
+            ("A3", "A3", "P1"),
+            ("B3", "C4", "m2"),
+        ];
+
+        for (from, to, expected) in cases {
+            let found = note(from).interval_to(note(to)).unwrap();
+            assert_eq!(found.to_string(), expected, "{} -> {}", from, to);
+            assert_eq!(
+                note(from).transpose(found).unwrap().to_string(),
+                note(to).to_string()
+            );
+        }
+
+        assert!(note("E4").interval_to(note("C4")).is_none());
+        assert!(note("C4").interval_to(note("Fbb4")).is_none());
+    }
+}
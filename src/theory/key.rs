@@ -0,0 +1,355 @@
+use crate::theory::scale::{Scale, ScaleKind};
+use crate::theory::{Accidental, Note, WhiteKey};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{error, fmt, str};
+
+/// Letter indexes in the order sharps are added to a key signature. Flats are
+/// added in the reverse order.
+const SHARP_ORDER: [i32; 7] = [3, 0, 4, 1, 5, 2, 6];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A major or minor key, identified by its signature: the number of sharps
+/// (positive) or flats (negative) from -7 to 7.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Key {
+    fifths: i32,
+    mode: Mode,
+}
+
+impl Key {
+    pub fn new(fifths: i32, mode: Mode) -> Option<Key> {
+        (-7..=7).contains(&fifths).then_some(Key { fifths, mode })
+    }
+
+    /// All 30 keys, majors first, each ordered from 7 flats to 7 sharps.
+    pub fn all() -> Vec<Key> {
+        [Mode::Major, Mode::Minor]
+            .into_iter()
+            .flat_map(|mode| (-7..=7).map(move |fifths| Key { fifths, mode }))
+            .collect()
+    }
+
+    pub fn fifths(&self) -> i32 {
+        self.fifths
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn tonic(&self) -> Note {
+        // a minor key shares its signature with the major key a minor third up,
+        // which puts its tonic three steps further round the circle of fifths
+        let fifths = match self.mode {
+            Mode::Major => self.fifths,
+            Mode::Minor => self.fifths + 3,
+        };
+        let white_key = WhiteKey::from_index(4 * fifths);
+        let offset = (7 * fifths - white_key.semitones_from_c() + 6).rem_euclid(12) - 6;
+
+        Note {
+            white_key,
+            accidental: Accidental::from_semitone_offset(offset).filter(|_| offset != 0),
+            octave: 4,
+        }
+    }
+
+    pub fn scale(&self) -> Scale {
+        let kind = match self.mode {
+            Mode::Major => ScaleKind::Major,
+            Mode::Minor => ScaleKind::NaturalMinor,
+        };
+        Scale::new(self.tonic(), kind)
+    }
+
+    /// The accidental the signature applies to `white_key`, if any.
+    pub fn accidental_for(&self, white_key: WhiteKey) -> Option<Accidental> {
+        let position = SHARP_ORDER.iter().position(|&i| i == white_key.index())? as i32;
+
+        if position < self.fifths {
+            Some(Accidental::Sharp)
+        } else if 6 - position < -self.fifths {
+            Some(Accidental::Flat)
+        } else {
+            None
+        }
+    }
+
+    /// The signature's accidentals in the order they're written on a staff.
+    pub fn signature_accidentals(&self) -> Vec<(WhiteKey, Accidental)> {
+        if self.fifths >= 0 {
+            SHARP_ORDER[..self.fifths as usize]
+                .iter()
+                .map(|&i| (WhiteKey::from_index(i), Accidental::Sharp))
+                .collect()
+        } else {
+            SHARP_ORDER
+                .iter()
+                .rev()
+                .take(-self.fifths as usize)
+                .map(|&i| (WhiteKey::from_index(i), Accidental::Flat))
+                .collect()
+        }
+    }
+
+    fn offset_for(&self, white_key: WhiteKey) -> i32 {
+        self.accidental_for(white_key)
+            .map_or(0, |a| a.semitone_offset())
+    }
+
+    fn pitch_class_of(&self, white_key: WhiteKey) -> i32 {
+        (white_key.semitones_from_c() + self.offset_for(white_key)).rem_euclid(12)
+    }
+
+    fn letter_with_pitch_class(&self, pitch_class: i32) -> Option<WhiteKey> {
+        (0..7)
+            .map(WhiteKey::from_index)
+            .find(|&key| self.pitch_class_of(key) == pitch_class.rem_euclid(12))
+    }
+
+    pub fn is_diatonic(&self, midi: i32) -> bool {
+        self.letter_with_pitch_class(midi).is_some()
+    }
+
+    /// Spells `midi` the way this key would. Diatonic notes take the signature's
+    /// spelling. The leading tone of a minor key is a raised seventh (G# in A
+    /// minor, C# in D minor). Other chromatic notes cancel the signature with a
+    /// natural where they can, otherwise they raise the diatonic note below in
+    /// sharp keys and lower the one above in flat keys.
+    pub fn spell(&self, midi: i32) -> Note {
+        let tonic = self.tonic();
+        let is_leading_tone = (tonic.pitch_class() - midi).rem_euclid(12) == 1;
+
+        let (white_key, offset) = if let Some(key) = self.letter_with_pitch_class(midi) {
+            (key, self.offset_for(key))
+        } else if self.mode == Mode::Minor && is_leading_tone {
+            let key = WhiteKey::from_index(tonic.white_key.index() + 6);
+            (key, self.offset_for(key) + 1)
+        } else if let Some(key) = (0..7)
+            .map(WhiteKey::from_index)
+            .find(|key| key.semitones_from_c() == midi.rem_euclid(12))
+        {
+            (key, 0)
+        } else if self.fifths >= 0 {
+            let key = self
+                .letter_with_pitch_class(midi - 1)
+                .expect("chromatic notes have a diatonic note below");
+            (key, self.offset_for(key) + 1)
+        } else {
+            let key = self
+                .letter_with_pitch_class(midi + 1)
+                .expect("chromatic notes have a diatonic note above");
+            (key, self.offset_for(key) - 1)
+        };
+
+        let note = Note {
+            white_key,
+            accidental: Accidental::from_semitone_offset(offset).filter(|_| offset != 0),
+            octave: 4,
+        };
+        Note {
+            octave: note.octave + (midi - note.midi_num()).div_euclid(12),
+            ..note
+        }
+    }
+
+    /// The accidental that has to be drawn next to `note` on a staff with this
+    /// key signature, or `None` when the signature already implies it.
+    pub fn displayed_accidental(&self, note: &Note) -> Option<Accidental> {
+        let note_offset = note.accidental.map_or(0, |a| a.semitone_offset());
+
+        if note_offset == self.offset_for(note.white_key) {
+            None
+        } else {
+            note.accidental.or(Some(Accidental::Natural))
+        }
+    }
+
+    /// Picks a random note between the midi numbers spelled for this key, from
+    /// the diatonic notes only unless `chromatic` is set. Returns `None` if no
+    /// such note is in range.
+    pub fn rand_in_range<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        low_midi: i32,
+        high_midi: i32,
+        chromatic: bool,
+    ) -> Option<Note> {
+        let midis: Vec<i32> = (low_midi..=high_midi)
+            .filter(|&midi| chromatic || self.is_diatonic(midi))
+            .collect();
+
+        midis.choose(rng).map(|&midi| self.spell(midi))
+    }
+
+    /// Key name as used by VexFlow's `addKeySignature`, e.g. "Bb" or "F#m".
+    pub fn signature(&self) -> String {
+        match self.mode {
+            Mode::Major => self.tonic().name(),
+            Mode::Minor => format!("{}m", self.tonic().name()),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(f, "{} {}", self.tonic().name(), mode)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseKeyError;
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a key signature like Bb or F#m")
+    }
+}
+
+impl error::Error for ParseKeyError {}
+
+/// Parses the same form `Key::signature` produces.
+impl str::FromStr for Key {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Key::all()
+            .into_iter()
+            .find(|key| key.signature() == s.trim())
+            .ok_or(ParseKeyError)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::key::*;
+    use std::str::FromStr;
+
+    fn key(s: &str) -> Key {
+        Key::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_key_tonics() {
+        let majors: Vec<String> = Key::all()[..15].iter().map(|k| k.signature()).collect();
+        assert_eq!(majors.join(" "), "Cb Gb Db Ab Eb Bb F C G D A E B F# C#");
+
+        let minors: Vec<String> = Key::all()[15..].iter().map(|k| k.signature()).collect();
+        assert_eq!(
+            minors.join(" "),
+            "Abm Ebm Bbm Fm Cm Gm Dm Am Em Bm F#m C#m G#m D#m A#m"
+        );
+
+        assert_eq!(Key::all().len(), 30);
+        assert_eq!(key("Eb").to_string(), "Eb major");
+        assert_eq!(key("F#m").fifths(), 3);
+        assert_eq!(Key::from_str("H"), Err(ParseKeyError));
+    }
+
+    #[test]
+    fn test_key_signature_accidentals() {
+        let sig = |k: Key| -> Vec<String> {
+            (0..7)
+                .map(WhiteKey::from_index)
+                .filter_map(|w| k.accidental_for(w).map(|a| format!("{}{}", w, a)))
+                .collect()
+        };
+
+        assert_eq!(sig(key("C")), Vec::<String>::new());
+        assert_eq!(sig(key("D")), vec!["C#", "F#"]);
+        assert_eq!(sig(key("Bb")), vec!["Eb", "Bb"]);
+        assert_eq!(sig(key("C#")).len(), 7);
+        assert_eq!(sig(key("Abm")).len(), 7);
+
+        let written = |k: &str| -> Vec<String> {
+            key(k)
+                .signature_accidentals()
+                .iter()
+                .map(|(w, a)| format!("{}{}", w, a))
+                .collect()
+        };
+        assert_eq!(written("A"), vec!["F#", "C#", "G#"]);
+        assert_eq!(written("Cm"), vec!["Bb", "Eb", "Ab"]);
+        assert_eq!(written("Am"), Vec::<String>::new());
+
+        for k in Key::all() {
+            let names: Vec<String> = k.scale().notes().iter().map(|n| n.name()).collect();
+            let from_sig: Vec<String> = k
+                .scale()
+                .notes()
+                .iter()
+                .map(|n| k.spell(n.midi_num()).name())
+                .collect();
+            assert_eq!(names, from_sig, "{}", k);
+        }
+    }
+
+    #[test]
+    fn test_key_spelling() {
+        let spell = |k: &str, midi: i32| key(k).spell(midi).to_string();
+
+        assert_eq!(spell("C", 66), "F#/4");
+        assert_eq!(spell("F", 61), "Db/4");
+        assert_eq!(spell("Am", 68), "G#/4");
+        assert_eq!(spell("Dm", 61), "C#/4");
+        assert_eq!(spell("Gb", 59), "Cb/4");
+        assert_eq!(spell("C#", 60), "B#/3");
+        assert_eq!(spell("E", 62), "D/4");
+        assert_eq!(spell("Eb", 40), "E/2");
+        assert_eq!(spell("Eb", 42), "Gb/2");
+
+        for k in Key::all() {
+            for midi in 40..=68 {
+                assert_eq!(k.spell(midi).midi_num(), midi, "{} {}", k, midi);
+            }
+        }
+    }
+
+    #[test]
+    fn test_displayed_accidental() {
+        let d_major = key("D");
+        let note = |s: &str| Note::from_str(s).unwrap();
+
+        assert!(d_major.displayed_accidental(&note("F#4")).is_none());
+        assert!(d_major.displayed_accidental(&note("G4")).is_none());
+        assert!(matches!(
+            d_major.displayed_accidental(&note("F4")),
+            Some(Accidental::Natural)
+        ));
+        assert!(matches!(
+            d_major.displayed_accidental(&note("Bb4")),
+            Some(Accidental::Flat)
+        ));
+    }
+
+    #[test]
+    fn test_key_rand_in_range() {
+        let mut rng = rand::thread_rng();
+        let g_major = key("G");
+        for _ in 0..50 {
+            let note = g_major.rand_in_range(&mut rng, 40, 68, false).unwrap();
+            assert!(g_major.is_diatonic(note.midi_num()));
+            assert!(note.accidental.is_none() || note.name() == "F#");
+        }
+
+        assert!(key("C").rand_in_range(&mut rng, 61, 61, false).is_none());
+        assert_eq!(
+            key("F")
+                .rand_in_range(&mut rng, 61, 61, true)
+                .unwrap()
+                .to_string(),
+            "Db/4"
+        );
+    }
+}
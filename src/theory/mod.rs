@@ -0,0 +1,1406 @@
+use crate::theory::clef::Clef;
+use rand::{
+    distributions::{Distribution, Standard},
+    seq::SliceRandom,
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::{error, fmt, num, str};
+
+pub mod chord;
+pub mod clef;
+pub mod interval;
+pub mod key;
+pub mod scale;
+pub mod tab;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Accidental {
+    DoubleFlat,
+    Flat,
+    Natural,
+    Sharp,
+    DoubleSharp,
+}
+
+impl Accidental {
+    fn semitone_offset(&self) -> i32 {
+        match self {
+            Self::DoubleFlat => -2,
+            Self::Flat => -1,
+            Self::Natural => 0,
+            Self::Sharp => 1,
+            Self::DoubleSharp => 2,
+        }
+    }
+
+    fn from_semitone_offset(offset: i32) -> Option<Accidental> {
+        match offset {
+            -2 => Some(Self::DoubleFlat),
+            -1 => Some(Self::Flat),
+            0 => Some(Self::Natural),
+            1 => Some(Self::Sharp),
+            2 => Some(Self::DoubleSharp),
+            _ => None,
+        }
+    }
+
+    const ALL: [Accidental; 5] = [
+        Self::DoubleFlat,
+        Self::Flat,
+        Self::Natural,
+        Self::Sharp,
+        Self::DoubleSharp,
+    ];
+
+    fn maybe_rand<R: Rng + ?Sized>(rng: &mut R) -> Option<Accidental> {
+        let rand: f64 = rng.gen();
+
+        if rand < 0.5 {
+            None
+        } else {
+            Some(rng.gen())
+        }
+    }
+}
+
+impl fmt::Display for Accidental {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::DoubleFlat => "bb",
+                Self::Flat => "b",
+                Self::Natural => "n",
+                Self::Sharp => "#",
+                Self::DoubleSharp => "##",
+            }
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseAccidentalError;
+
+impl fmt::Display for ParseAccidentalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected one of bb, b, n, # or ##")
+    }
+}
+
+impl error::Error for ParseAccidentalError {}
+
+impl str::FromStr for Accidental {
+    type Err = ParseAccidentalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bb" => Ok(Self::DoubleFlat),
+            "b" => Ok(Self::Flat),
+            "n" => Ok(Self::Natural),
+            "#" => Ok(Self::Sharp),
+            "##" => Ok(Self::DoubleSharp),
+            _ => Err(ParseAccidentalError),
+        }
+    }
+}
+
+impl Distribution<Accidental> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Accidental {
+        match rng.gen_range(0..5) {
+            0 => Accidental::DoubleFlat,
+            1 => Accidental::Flat,
+            2 => Accidental::Natural,
+            3 => Accidental::Sharp,
+            _ => Accidental::DoubleSharp,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum WhiteKey {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B,
+}
+
+impl WhiteKey {
+    fn semitones_from_c(&self) -> i32 {
+        match &self {
+            Self::C => 0,
+            Self::D => 2,
+            Self::E => 4,
+            Self::F => 5,
+            Self::G => 7,
+            Self::A => 9,
+            Self::B => 11,
+        }
+    }
+
+    /// Position of the letter within the octave, C = 0 through B = 6.
+    fn index(&self) -> i32 {
+        match &self {
+            Self::C => 0,
+            Self::D => 1,
+            Self::E => 2,
+            Self::F => 3,
+            Self::G => 4,
+            Self::A => 5,
+            Self::B => 6,
+        }
+    }
+
+    fn from_index(index: i32) -> WhiteKey {
+        match index.rem_euclid(7) {
+            0 => Self::C,
+            1 => Self::D,
+            2 => Self::E,
+            3 => Self::F,
+            4 => Self::G,
+            5 => Self::A,
+            _ => Self::B,
+        }
+    }
+}
+
+impl fmt::Display for WhiteKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseWhiteKeyError;
+
+impl fmt::Display for ParseWhiteKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a note letter from A to G")
+    }
+}
+
+impl error::Error for ParseWhiteKeyError {}
+
+impl str::FromStr for WhiteKey {
+    type Err = ParseWhiteKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "C" => Ok(Self::C),
+            "D" => Ok(Self::D),
+            "E" => Ok(Self::E),
+            "F" => Ok(Self::F),
+            "G" => Ok(Self::G),
+            "A" => Ok(Self::A),
+            "B" => Ok(Self::B),
+            _ => Err(ParseWhiteKeyError),
+        }
+    }
+}
+
+impl Distribution<WhiteKey> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> WhiteKey {
+        match rng.gen_range(0..7) {
+            0 => WhiteKey::C,
+            1 => WhiteKey::D,
+            2 => WhiteKey::E,
+            3 => WhiteKey::F,
+            4 => WhiteKey::G,
+            5 => WhiteKey::A,
+            _ => WhiteKey::B,
+        }
+    }
+}
+
+type Octave = i32;
+
+/// Concert pitch, the usual reference for `Note::frequency`.
+pub const A4_HZ: f64 = 440.0;
+
+const A4_MIDI: i32 = 69;
+
+/// Highest midi note number, G9.
+pub const MAX_MIDI: i32 = 127;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Note {
+    pub white_key: WhiteKey,
+    pub octave: Octave,
+    pub accidental: Option<Accidental>,
+}
+
+impl Note {
+    fn pitch_class(&self) -> i32 {
+        let acc_offset = &self.accidental.as_ref().map_or(0, |a| a.semitone_offset());
+        self.white_key.semitones_from_c() + acc_offset
+    }
+
+    pub fn midi_num(&self) -> i32 {
+        self.pitch_class() + 12 * (self.octave + 1)
+    }
+
+    /// Whether the note has a midi number, 0 to `MAX_MIDI`. Safe to call on
+    /// octaves so far out that `midi_num` would overflow.
+    pub fn is_midi(&self) -> bool {
+        (-2..=10).contains(&self.octave) && (0..=MAX_MIDI).contains(&self.midi_num())
+    }
+
+    /// Frequency in Hz in twelve-tone equal temperament, tuned so that A4
+    /// sounds at `a4_hz`.
+    pub fn frequency(&self, a4_hz: f64) -> f64 {
+        let semitones = (self.midi_num() - A4_MIDI) as f64;
+        a4_hz * 2f64.powf(semitones / 12.0)
+    }
+
+    /// Finds the nearest note to `hz`, spelled with sharps, along with how far
+    /// `hz` is from it in cents (-50 to 50). Returns `None` unless `hz` and
+    /// `a4_hz` are positive.
+    pub fn from_frequency(hz: f64, a4_hz: f64) -> Option<(Note, f64)> {
+        if !(hz > 0.0 && hz.is_finite() && a4_hz > 0.0) {
+            return None;
+        }
+
+        let semitones = 12.0 * (hz / a4_hz).log2();
+        let nearest = semitones.round();
+        let note = Note::from_midi(A4_MIDI + nearest as i32);
+        let cents = 100.0 * (semitones - nearest);
+
+        Some((note, cents))
+    }
+
+    /// Number of letter steps above C/0, ignoring accidentals. Notes on the
+    /// same staff line or space share this number.
+    fn diatonic_num(&self) -> i32 {
+        self.white_key.index() + 7 * self.octave
+    }
+
+    pub fn is_enharmonic(&self, other: Note) -> bool {
+        self.midi_num() == other.midi_num()
+    }
+
+    fn accidental_offset(&self) -> i32 {
+        self.accidental.map_or(0, |a| a.semitone_offset())
+    }
+
+    /// Spells `midi` on the given letter, if it takes at most a double
+    /// accidental to get there.
+    fn spelled_on(white_key: WhiteKey, midi: i32) -> Option<Note> {
+        let natural = Note {
+            white_key,
+            accidental: None,
+            octave: 4,
+        };
+        let distance = midi - natural.midi_num();
+        let offset = (distance + 6).rem_euclid(12) - 6;
+        let accidental = Accidental::from_semitone_offset(offset)?;
+
+        Some(Note {
+            white_key,
+            accidental: (offset != 0).then_some(accidental),
+            octave: natural.octave + (distance - offset).div_euclid(12),
+        })
+    }
+
+    /// Every spelling of this pitch with at most a double accidental, from the
+    /// lowest letter up. Includes this note's own spelling.
+    pub fn enharmonics(&self) -> Vec<Note> {
+        let midi = self.midi_num();
+        let mut notes: Vec<Note> = (0..7)
+            .filter_map(|i| Note::spelled_on(WhiteKey::from_index(i), midi))
+            .collect();
+        notes.sort_by_key(Note::diatonic_num);
+        notes
+    }
+
+    /// Respells with a natural if possible, otherwise with a single sharp or
+    /// flat according to `preference`.
+    pub fn respell(&self, preference: AccidentalPreference) -> Note {
+        let wanted = match preference {
+            AccidentalPreference::Sharps => 1,
+            AccidentalPreference::Flats => -1,
+        };
+        let enharmonics = self.enharmonics();
+
+        enharmonics
+            .iter()
+            .find(|n| n.accidental.is_none())
+            .or_else(|| enharmonics.iter().find(|n| n.accidental_offset() == wanted))
+            .copied()
+            .expect("every pitch has a natural, sharp and flat spelling")
+    }
+
+    /// Respells with the fewest accidentals, keeping the direction of the
+    /// current accidental: Fb becomes E, E## becomes F# and Fbb becomes Eb.
+    pub fn simplify(&self) -> Note {
+        if self.accidental_offset() > 0 {
+            self.respell(AccidentalPreference::Sharps)
+        } else {
+            self.respell(AccidentalPreference::Flats)
+        }
+    }
+
+    /// Orders notes by pitch only, so enharmonics compare as equal.
+    pub fn cmp_pitch(&self, other: &Note) -> Ordering {
+        self.midi_num().cmp(&other.midi_num())
+    }
+
+    /// Spells a midi number using naturals and sharps, e.g. 61 => C#/4.
+    pub fn from_midi(midi: i32) -> Note {
+        let (white_key, accidental) = match midi.rem_euclid(12) {
+            0 => (WhiteKey::C, None),
+            1 => (WhiteKey::C, Some(Accidental::Sharp)),
+            2 => (WhiteKey::D, None),
+            3 => (WhiteKey::D, Some(Accidental::Sharp)),
+            4 => (WhiteKey::E, None),
+            5 => (WhiteKey::F, None),
+            6 => (WhiteKey::F, Some(Accidental::Sharp)),
+            7 => (WhiteKey::G, None),
+            8 => (WhiteKey::G, Some(Accidental::Sharp)),
+            9 => (WhiteKey::A, None),
+            10 => (WhiteKey::A, Some(Accidental::Sharp)),
+            _ => (WhiteKey::B, None),
+        };
+
+        Note {
+            white_key,
+            accidental,
+            octave: midi.div_euclid(12) - 1,
+        }
+    }
+
+    /// Note name without the octave, e.g. "C#".
+    pub fn name(&self) -> String {
+        let accidental = self
+            .accidental
+            .as_ref()
+            .map_or(String::from(""), |a| a.to_string());
+
+        format!("{}{}", self.white_key, accidental)
+    }
+
+    /// Picks a random spelling of a note between the midi numbers, weighted
+    /// like `Standard` samples notes: half of the time without an accidental.
+    /// Returns `None` if the range is empty.
+    pub fn rand_in_range<R: Rng + ?Sized>(
+        rng: &mut R,
+        low_midi: i32,
+        high_midi: i32,
+    ) -> Option<Note> {
+        // accidentals can push a note across an octave boundary, so allow one
+        // extra octave on either side of the range
+        let octaves = (low_midi.div_euclid(12) - 2)..=high_midi.div_euclid(12);
+        let mut accidentals = [None; 6];
+        for (slot, acc) in accidentals[1..].iter_mut().zip(Accidental::ALL) {
+            *slot = Some(acc);
+        }
+
+        let candidates: Vec<Note> = octaves
+            .flat_map(|octave| {
+                (0..7).flat_map(move |i| {
+                    accidentals.into_iter().map(move |accidental| Note {
+                        white_key: WhiteKey::from_index(i),
+                        accidental,
+                        octave,
+                    })
+                })
+            })
+            .filter(|note| (low_midi..=high_midi).contains(&note.midi_num()))
+            .collect();
+
+        candidates
+            .choose_weighted(rng, |note| match note.accidental {
+                None => Accidental::ALL.len(),
+                Some(_) => 1,
+            })
+            .ok()
+            .copied()
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.name(), self.octave)
+    }
+}
+
+/// Notes are equal when they are spelled the same. A natural sign is the same
+/// spelling as no accidental, but enharmonics like C# and Db are different
+/// notes. Wrap notes in `ByPitch` to compare them by pitch instead.
+impl PartialEq for Note {
+    fn eq(&self, other: &Self) -> bool {
+        self.white_key == other.white_key
+            && self.octave == other.octave
+            && self.accidental_offset() == other.accidental_offset()
+    }
+}
+
+impl Eq for Note {}
+
+impl Hash for Note {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.white_key.hash(state);
+        self.octave.hash(state);
+        self.accidental_offset().hash(state);
+    }
+}
+
+/// Orders by pitch, then by letter, so B#/3 < C/4 < Dbb/4.
+impl Ord for Note {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_pitch(other)
+            .then_with(|| self.diatonic_num().cmp(&other.diatonic_num()))
+    }
+}
+
+impl PartialOrd for Note {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares, orders and hashes a note by pitch alone, for sets and maps where
+/// enharmonics should count as the same note.
+#[derive(Clone, Copy, Debug)]
+pub struct ByPitch(pub Note);
+
+impl PartialEq for ByPitch {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_enharmonic(other.0)
+    }
+}
+
+impl Eq for ByPitch {}
+
+impl Hash for ByPitch {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.midi_num().hash(state);
+    }
+}
+
+impl Ord for ByPitch {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_pitch(&other.0)
+    }
+}
+
+impl PartialOrd for ByPitch {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AccidentalPreference {
+    Sharps,
+    Flats,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseNoteError {
+    WhiteKey(ParseWhiteKeyError),
+    Accidental(ParseAccidentalError),
+    Octave(num::ParseIntError),
+}
+
+impl fmt::Display for ParseNoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WhiteKey(e) => write!(f, "invalid note letter: {}", e),
+            Self::Accidental(e) => write!(f, "invalid accidental: {}", e),
+            Self::Octave(e) => write!(f, "invalid octave: {}", e),
+        }
+    }
+}
+
+impl error::Error for ParseNoteError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::WhiteKey(e) => Some(e),
+            Self::Accidental(e) => Some(e),
+            Self::Octave(e) => Some(e),
+        }
+    }
+}
+
+impl From<ParseWhiteKeyError> for ParseNoteError {
+    fn from(e: ParseWhiteKeyError) -> Self {
+        Self::WhiteKey(e)
+    }
+}
+
+impl From<ParseAccidentalError> for ParseNoteError {
+    fn from(e: ParseAccidentalError) -> Self {
+        Self::Accidental(e)
+    }
+}
+
+impl From<num::ParseIntError> for ParseNoteError {
+    fn from(e: num::ParseIntError) -> Self {
+        Self::Octave(e)
+    }
+}
+
+/// Parses scientific pitch notation (`C#4`, `Eb3`) as well as the VexFlow
+/// form produced by `Display` (`C#/4`).
+impl str::FromStr for Note {
+    type Err = ParseNoteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let key_len = s.chars().next().map_or(0, |c| c.len_utf8());
+        let white_key = s[..key_len].parse::<WhiteKey>()?;
+
+        let rest = &s[key_len..];
+        let acc_len = rest
+            .find(|c| !matches!(c, '#' | 'b' | 'n'))
+            .unwrap_or(rest.len());
+        let accidental = match &rest[..acc_len] {
+            "" => None,
+            acc => Some(acc.parse::<Accidental>()?),
+        };
+
+        let octave_str = &rest[acc_len..];
+        let octave = octave_str
+            .strip_prefix('/')
+            .unwrap_or(octave_str)
+            .parse::<Octave>()?;
+
+        Ok(Note {
+            white_key,
+            accidental,
+            octave,
+        })
+    }
+}
+
+impl Distribution<Note> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Note {
+        Note {
+            white_key: rng.gen(),
+            accidental: Accidental::maybe_rand(rng),
+            octave: rng.gen_range(3..=7),
+        }
+    }
+}
+
+/// A position on the fretboard. Strings are numbered from 1, starting with the
+/// highest-pitched string, the same way `fretboard.js` numbers them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct FretCoord {
+    pub string: i32,
+    pub fret: i32,
+}
+
+/// Open string notes, indexed by string number - 1 (highest string first).
+pub type Tuning = Vec<Note>;
+
+/// Most strings a tuning can have, enough for a 12-string guitar.
+pub const MAX_STRINGS: usize = 12;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseTuningError {
+    Note(ParseNoteError),
+    /// Tunings need 1 to `MAX_STRINGS` strings.
+    StringCount(usize),
+}
+
+impl fmt::Display for ParseTuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Note(e) => write!(f, "invalid string note: {}", e),
+            Self::StringCount(n) => {
+                write!(f, "expected 1 to {} strings, found {}", MAX_STRINGS, n)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseTuningError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Note(e) => Some(e),
+            Self::StringCount(_) => None,
+        }
+    }
+}
+
+impl From<ParseNoteError> for ParseTuningError {
+    fn from(e: ParseNoteError) -> Self {
+        Self::Note(e)
+    }
+}
+
+/// Parses a tuning written the way players write it, from the lowest string
+/// (the one nearest your face) to string 1, e.g. "E2 A2 D3 G3 B3 E4".
+pub fn parse_tuning(s: &str) -> Result<Tuning, ParseTuningError> {
+    let mut tuning = s
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Tuning, _>>()?;
+    if !(1..=MAX_STRINGS).contains(&tuning.len()) {
+        return Err(ParseTuningError::StringCount(tuning.len()));
+    }
+    tuning.reverse();
+    Ok(tuning)
+}
+
+/// Writes a tuning the way `parse_tuning` reads it, lowest string first.
+pub fn format_tuning(tuning: &Tuning) -> String {
+    tuning
+        .iter()
+        .rev()
+        .map(|note| format!("{}{}", note.name(), note.octave))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TuningPreset {
+    Standard,
+    DropD,
+    Dadgad,
+    OpenG,
+    SevenString,
+    Bass,
+    FiveStringBass,
+    Ukulele,
+    Banjo,
+}
+
+impl TuningPreset {
+    pub const ALL: [TuningPreset; 9] = [
+        Self::Standard,
+        Self::DropD,
+        Self::Dadgad,
+        Self::OpenG,
+        Self::SevenString,
+        Self::Bass,
+        Self::FiveStringBass,
+        Self::Ukulele,
+        Self::Banjo,
+    ];
+
+    pub fn notation(&self) -> &'static str {
+        match self {
+            Self::Standard => "E2 A2 D3 G3 B3 E4",
+            Self::DropD => "D2 A2 D3 G3 B3 E4",
+            Self::Dadgad => "D2 A2 D3 G3 A3 D4",
+            Self::OpenG => "D2 G2 D3 G3 B3 D4",
+            Self::SevenString => "B1 E2 A2 D3 G3 B3 E4",
+            Self::Bass => "E1 A1 D2 G2",
+            Self::FiveStringBass => "B0 E1 A1 D2 G2",
+            // reentrant: the 4th string is tuned above the 3rd
+            Self::Ukulele => "G4 C4 E4 A4",
+            // open G, with the short 5th string first
+            Self::Banjo => "G4 D3 G3 B3 D4",
+        }
+    }
+
+    pub fn tuning(&self) -> Tuning {
+        parse_tuning(self.notation()).expect("preset tunings are valid")
+    }
+
+    /// The clef music for the instrument is usually written in.
+    pub fn clef(&self) -> Clef {
+        match self {
+            Self::Bass | Self::FiveStringBass => Clef::Bass8vb,
+            Self::Ukulele => Clef::Treble,
+            _ => Clef::Treble8vb,
+        }
+    }
+
+    /// Specs for strings that aren't plain, indexed like the tuning.
+    pub fn string_specs(&self) -> Vec<StringSpec> {
+        match self {
+            Self::Banjo => {
+                let mut specs = vec![StringSpec::default(); 5];
+                specs[4].nut_fret = 5;
+                specs
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for TuningPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Standard => "Standard",
+                Self::DropD => "Drop D",
+                Self::Dadgad => "DADGAD",
+                Self::OpenG => "Open G",
+                Self::SevenString => "7-String",
+                Self::Bass => "Bass",
+                Self::FiveStringBass => "5-String Bass",
+                Self::Ukulele => "Ukulele",
+                Self::Banjo => "5-String Banjo",
+            }
+        )
+    }
+}
+
+/// A capo clamped across the neck at `fret`. A partial capo only covers some
+/// strings, listed in `strings`; a full capo covers them all.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Capo {
+    pub fret: i32,
+    pub strings: Option<Vec<i32>>,
+}
+
+impl Capo {
+    pub fn full(fret: i32) -> Capo {
+        Capo {
+            fret,
+            strings: None,
+        }
+    }
+
+    pub fn partial(fret: i32, strings: Vec<i32>) -> Capo {
+        Capo {
+            fret,
+            strings: Some(strings),
+        }
+    }
+
+    pub fn covers(&self, string: i32) -> bool {
+        self.strings.as_ref().is_none_or(|s| s.contains(&string))
+    }
+}
+
+/// How a string differs from one that runs the whole neck with every fret.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StringSpec {
+    /// Replaces the fretboard's fret window on this string.
+    pub start_fret: Option<i32>,
+    pub end_fret: Option<i32>,
+    /// Leaves the string out, e.g. to drill only the low strings.
+    pub skipped: bool,
+    /// Where the string's own nut is, for short strings like the 5th string
+    /// of a banjo. Frets up to it don't exist on the string, frets past it
+    /// are still counted from the main nut.
+    pub nut_fret: i32,
+    /// Only ever played open.
+    pub drone: bool,
+    /// Frets the string doesn't have, like the frets a diatonic dulcimer
+    /// leaves out.
+    pub missing_frets: Vec<i32>,
+}
+
+static PLAIN_STRING: StringSpec = StringSpec {
+    start_fret: None,
+    end_fret: None,
+    skipped: false,
+    nut_fret: 0,
+    drone: false,
+    missing_frets: Vec::new(),
+};
+
+/// The strings and frets notes can be played on. Fret numbers in coords are
+/// always counted from the nut, but with a capo on, the fret window is counted
+/// from the capo on the strings it covers: with a capo at 2, frets 0 to 4 are
+/// frets 2 to 6 on those strings.
+#[derive(Clone, Debug)]
+pub struct Fretboard {
+    tuning: Tuning,
+    start_fret: i32,
+    end_fret: i32,
+    capo: Option<Capo>,
+    /// Indexed like the tuning. Strings without a spec are plain.
+    strings: Vec<StringSpec>,
+}
+
+impl Fretboard {
+    pub fn new(tuning: Tuning, start_fret: i32, end_fret: i32) -> Fretboard {
+        Fretboard {
+            tuning,
+            start_fret,
+            end_fret,
+            capo: None,
+            strings: vec![],
+        }
+    }
+
+    pub fn with_capo(self, capo: Option<Capo>) -> Fretboard {
+        Fretboard { capo, ..self }
+    }
+
+    pub fn with_strings(self, strings: Vec<StringSpec>) -> Fretboard {
+        Fretboard { strings, ..self }
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    pub fn start_fret(&self) -> i32 {
+        self.start_fret
+    }
+
+    pub fn end_fret(&self) -> i32 {
+        self.end_fret
+    }
+
+    pub fn capo(&self) -> Option<&Capo> {
+        self.capo.as_ref()
+    }
+
+    pub fn num_strings(&self) -> i32 {
+        self.tuning.len() as i32
+    }
+
+    pub fn string_spec(&self, string: i32) -> Option<&StringSpec> {
+        self.open_string(string)?;
+        Some(
+            self.strings
+                .get(string as usize - 1)
+                .unwrap_or(&PLAIN_STRING),
+        )
+    }
+
+    /// The fret the capo holds `string` down at, 0 when it's free. A capo
+    /// can't reach a short string below its nut.
+    pub fn capo_fret(&self, string: i32) -> i32 {
+        let nut_fret = self.string_spec(string).map_or(0, |spec| spec.nut_fret);
+
+        self.capo
+            .as_ref()
+            .filter(|capo| capo.covers(string) && capo.fret > nut_fret)
+            .map_or(0, |capo| capo.fret)
+    }
+
+    /// Lowest and highest frets, counted from the nut, of the window on
+    /// `string`, or `None` if there is no such string or it's skipped. Frets
+    /// inside the window can still be unplayable, see `is_playable`.
+    pub fn fret_range(&self, string: i32) -> Option<(i32, i32)> {
+        let spec = self.string_spec(string).filter(|spec| !spec.skipped)?;
+        let capo_fret = self.capo_fret(string);
+        let start_fret = spec.start_fret.unwrap_or(self.start_fret);
+        let end_fret = spec.end_fret.unwrap_or(self.end_fret);
+
+        Some((start_fret + capo_fret, end_fret + capo_fret))
+    }
+
+    /// Lowest and highest frets that can be played on any string.
+    pub fn fret_span(&self) -> (i32, i32) {
+        let ranges = (1..=self.num_strings()).filter_map(|s| self.fret_range(s));
+        let low = ranges.clone().map(|(low, _)| low).min();
+        let high = ranges.map(|(_, high)| high).max();
+
+        (
+            low.unwrap_or(self.start_fret),
+            high.unwrap_or(self.end_fret),
+        )
+    }
+
+    /// Whether `coord` is in its string's window on a fret the string has.
+    pub fn is_playable(&self, coord: &FretCoord) -> bool {
+        let (Some(spec), Some((low, high))) = (
+            self.string_spec(coord.string),
+            self.fret_range(coord.string),
+        ) else {
+            return false;
+        };
+        let fret = coord.fret;
+        let open_fret = self.capo_fret(coord.string);
+
+        (low..=high).contains(&fret)
+            && (fret == 0 || fret > spec.nut_fret)
+            && !spec.missing_frets.contains(&fret)
+            && (!spec.drone || fret == open_fret)
+    }
+
+    fn midi_at(&self, coord: &FretCoord) -> Option<i32> {
+        let open = self.open_string(coord.string)?.midi_num();
+        let nut_fret = self.string_spec(coord.string)?.nut_fret;
+
+        Some(match coord.fret {
+            0 => open,
+            fret => open + fret - nut_fret,
+        })
+    }
+
+    /// The note `string` sounds when it isn't fretted, which is the capo's
+    /// note if it covers the string.
+    pub fn open_note(&self, string: i32) -> Option<Note> {
+        let fret = self.capo_fret(string);
+        self.midi_at(&FretCoord { string, fret })
+            .map(Note::from_midi)
+    }
+
+    /// Returns the lowest and highest midi numbers playable on this fretboard.
+    pub fn midi_range(&self) -> Option<(i32, i32)> {
+        let midis = self.coords().filter_map(|coord| self.midi_at(&coord));
+        let low = midis.clone().min()?;
+        let high = midis.max()?;
+
+        Some((low, high))
+    }
+
+    /// Every playable coord on the fretboard, string by string from string 1.
+    pub fn coords(&self) -> impl Iterator<Item = FretCoord> + Clone + '_ {
+        (1..=self.num_strings()).flat_map(move |string| {
+            let (low, high) = self.fret_range(string).unwrap_or((0, -1));
+            (low..=high)
+                .map(move |fret| FretCoord { string, fret })
+                .filter(move |coord| self.is_playable(coord))
+        })
+    }
+
+    fn open_string(&self, string: i32) -> Option<&Note> {
+        if string < 1 {
+            return None;
+        }
+        self.tuning.get(string as usize - 1)
+    }
+
+    /// Returns the note sounding at `coord`, spelled with sharps, or `None` if
+    /// the coord is not on this fretboard.
+    pub fn note_at(&self, coord: &FretCoord) -> Option<Note> {
+        if !self.is_playable(coord) {
+            return None;
+        }
+
+        self.midi_at(coord).map(Note::from_midi)
+    }
+
+    /// Returns every playable coord where `note` can be played. Any enharmonic
+    /// spelling matches, but the octave must be the same.
+    pub fn positions_of(&self, note: Note) -> Vec<FretCoord> {
+        let midi = note.midi_num();
+
+        self.coords()
+            .filter(|coord| self.midi_at(coord) == Some(midi))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::*;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_parse_note() {
+        let c_double_sharp = Note {
+            white_key: WhiteKey::C,
+            accidental: Some(Accidental::DoubleSharp),
+            octave: 4,
+        };
+
+        let e_double_flat = Note {
+            white_key: WhiteKey::E,
+            accidental: Some(Accidental::DoubleFlat),
+            octave: 4,
+        };
+
+        assert!(c_double_sharp.is_enharmonic(e_double_flat));
+    }
+
+    #[test]
+    fn test_parse_note_from_str() {
+        let note = Note::from_str("C#4").unwrap();
+        assert!(matches!(note.white_key, WhiteKey::C));
+        assert!(matches!(note.accidental, Some(Accidental::Sharp)));
+        assert_eq!(note.octave, 4);
+
+        assert_eq!(Note::from_str("Eb3").unwrap().to_string(), "Eb/3");
+        assert_eq!(Note::from_str("C#/4").unwrap().to_string(), "C#/4");
+        assert_eq!(Note::from_str("Bbb/2").unwrap().to_string(), "Bbb/2");
+        assert_eq!(Note::from_str("Fn5").unwrap().to_string(), "Fn/5");
+        assert_eq!(Note::from_str("C-1").unwrap().midi_num(), 0);
+
+        assert_eq!(
+            Note::from_str("H4").unwrap_err(),
+            ParseNoteError::WhiteKey(ParseWhiteKeyError)
+        );
+        assert_eq!(
+            Note::from_str("C###4").unwrap_err(),
+            ParseNoteError::Accidental(ParseAccidentalError)
+        );
+        assert!(matches!(
+            Note::from_str("C#"),
+            Err(ParseNoteError::Octave(_))
+        ));
+        assert!(matches!(
+            Note::from_str(""),
+            Err(ParseNoteError::WhiteKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_note_round_trip() {
+        for midi in 0..128 {
+            let note = Note::from_midi(midi);
+            let parsed = Note::from_str(&note.to_string()).unwrap();
+            assert_eq!(parsed.to_string(), note.to_string());
+            assert_eq!(parsed.midi_num(), midi);
+        }
+
+        for _ in 0..100 {
+            let note: Note = rand::random();
+            let parsed = Note::from_str(&note.to_string()).unwrap();
+            assert_eq!(parsed.to_string(), note.to_string());
+        }
+    }
+
+    fn note(s: &str) -> Note {
+        Note::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_simplify_and_respell() {
+        let cases = [
+            ("Fb4", "E/4"),
+            ("B#3", "C/4"),
+            ("C##4", "D/4"),
+            ("E##4", "F#/4"),
+            ("Fbb4", "Eb/4"),
+            ("Cb4", "B/3"),
+            ("Cn4", "C/4"),
+            ("Db4", "Db/4"),
+        ];
+        for (from, to) in cases {
+            assert_eq!(note(from).simplify().to_string(), to, "{}", from);
+        }
+
+        assert_eq!(
+            note("Db4")
+                .respell(AccidentalPreference::Sharps)
+                .to_string(),
+            "C#/4"
+        );
+        assert_eq!(
+            note("A#2").respell(AccidentalPreference::Flats).to_string(),
+            "Bb/2"
+        );
+        assert_eq!(
+            note("B#3").respell(AccidentalPreference::Flats).to_string(),
+            "C/4"
+        );
+    }
+
+    #[test]
+    fn test_enharmonics() {
+        let names = |s: &str| -> Vec<String> {
+            note(s)
+                .enharmonics()
+                .iter()
+                .map(|n| n.to_string())
+                .collect()
+        };
+
+        assert_eq!(names("C4"), vec!["B#/3", "C/4", "Dbb/4"]);
+        assert_eq!(names("G#4"), vec!["G#/4", "Ab/4"]);
+        assert_eq!(names("Fb4"), vec!["D##/4", "E/4", "Fb/4"]);
+
+        for midi in 0..128 {
+            let note = Note::from_midi(midi);
+            assert!(note.enharmonics().contains(&note));
+            assert!(note.enharmonics().iter().all(|n| n.is_enharmonic(note)));
+        }
+    }
+
+    #[test]
+    fn test_note_comparisons() {
+        use std::collections::HashSet;
+
+        assert_eq!(note("C4"), note("Cn4"));
+        assert_ne!(note("C#4"), note("Db4"));
+        assert_ne!(note("C4"), note("C5"));
+        assert!(note("B#3") < note("C4"));
+        assert!(note("C4") < note("Dbb4"));
+        assert!(note("C4") < note("C#4"));
+        assert_eq!(note("C#4").cmp_pitch(&note("Db4")), Ordering::Equal);
+
+        let notes = ["C#4", "Db4", "C#4", "Cn4", "C4"].map(note);
+        let by_spelling: HashSet<Note> = notes.iter().copied().collect();
+        assert_eq!(by_spelling.len(), 3);
+
+        let by_pitch: HashSet<ByPitch> = notes.iter().copied().map(ByPitch).collect();
+        assert_eq!(by_pitch.len(), 2);
+        assert!(ByPitch(note("C#4")) == ByPitch(note("Db4")));
+        assert!(ByPitch(note("C4")) < ByPitch(note("Db4")));
+    }
+
+    #[test]
+    fn test_is_midi() {
+        assert!(Note::from_str("C-1").unwrap().is_midi());
+        assert!(Note::from_str("G9").unwrap().is_midi());
+        assert!(!Note::from_str("Cb-1").unwrap().is_midi());
+        assert!(!Note::from_str("G#9").unwrap().is_midi());
+        assert!(!Note::from_str("C99999999").unwrap().is_midi());
+        assert!(!Note::from_str("C-2147483648").unwrap().is_midi());
+    }
+
+    #[test]
+    fn test_frequency() {
+        let close = |a: f64, b: f64| (a - b).abs() < 0.01;
+
+        assert!(close(note("A4").frequency(A4_HZ), 440.0));
+        assert!(close(note("A3").frequency(A4_HZ), 220.0));
+        assert!(close(note("C4").frequency(A4_HZ), 261.63));
+        assert!(close(note("E2").frequency(A4_HZ), 82.41));
+        assert!(close(note("A4").frequency(432.0), 432.0));
+        assert!(close(note("Bbb4").frequency(A4_HZ), 440.0));
+    }
+
+    #[test]
+    fn test_from_frequency() {
+        let (found, cents) = Note::from_frequency(440.0, A4_HZ).unwrap();
+        assert_eq!(found, note("A4"));
+        assert!(cents.abs() < 1e-9);
+
+        let (found, cents) = Note::from_frequency(445.0, A4_HZ).unwrap();
+        assert_eq!(found, note("A4"));
+        assert!((cents - 19.56).abs() < 0.01);
+
+        let (found, cents) = Note::from_frequency(82.0, A4_HZ).unwrap();
+        assert_eq!(found, note("E2"));
+        assert!(cents < 0.0);
+
+        let (found, _) = Note::from_frequency(432.0, 432.0).unwrap();
+        assert_eq!(found, note("A4"));
+
+        for midi in 21..=108 {
+            let hz = Note::from_midi(midi).frequency(A4_HZ);
+            let (found, cents) = Note::from_frequency(hz, A4_HZ).unwrap();
+            assert_eq!(found.midi_num(), midi);
+            assert!(cents.abs() < 1e-6);
+        }
+
+        assert!(Note::from_frequency(0.0, A4_HZ).is_none());
+        assert!(Note::from_frequency(-1.0, A4_HZ).is_none());
+        assert!(Note::from_frequency(f64::NAN, A4_HZ).is_none());
+        assert!(Note::from_frequency(440.0, 0.0).is_none());
+    }
+
+    fn standard_fretboard(start_fret: i32, end_fret: i32) -> Fretboard {
+        Fretboard::new(TuningPreset::Standard.tuning(), start_fret, end_fret)
+    }
+
+    #[test]
+    fn test_parse_tuning() {
+        let tuning = parse_tuning("E2 A2 D3 G3 B3 E4").unwrap();
+        let midis: Vec<i32> = tuning.iter().map(Note::midi_num).collect();
+        assert_eq!(midis, vec![64, 59, 55, 50, 45, 40]);
+
+        let tuning = parse_tuning("  Eb2 Ab2 Db3  Gb3 Bb3 Eb4 ").unwrap();
+        assert_eq!(tuning[0].to_string(), "Eb/4");
+        assert_eq!(tuning.len(), 6);
+
+        assert!(parse_tuning("E2 A2 X3").is_err());
+
+        assert_eq!(parse_tuning(""), Err(ParseTuningError::StringCount(0)));
+        assert_eq!(parse_tuning("  "), Err(ParseTuningError::StringCount(0)));
+        assert_eq!(parse_tuning("E4").unwrap().len(), 1);
+        assert_eq!(parse_tuning(&["E2"; 12].join(" ")).unwrap().len(), 12);
+        assert_eq!(
+            parse_tuning(&["E2"; 13].join(" ")),
+            Err(ParseTuningError::StringCount(13))
+        );
+    }
+
+    #[test]
+    fn test_tuning_presets() {
+        for preset in TuningPreset::ALL {
+            assert!(!preset.tuning().is_empty());
+        }
+
+        let uke = Fretboard::new(TuningPreset::Ukulele.tuning(), 0, 4);
+        assert_eq!(uke.num_strings(), 4);
+        let open_g = uke.note_at(&FretCoord { string: 4, fret: 0 }).unwrap();
+        assert_eq!(open_g.to_string(), "G/4");
+        assert_eq!(uke.midi_range(), Some((60, 73)));
+
+        let bass = Fretboard::new(TuningPreset::FiveStringBass.tuning(), 0, 4);
+        assert_eq!(bass.midi_range(), Some((23, 47)));
+    }
+
+    #[test]
+    fn test_rand_in_range() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let midi = Note::rand_in_range(&mut rng, 23, 30).unwrap().midi_num();
+            assert!((23..=30).contains(&midi));
+        }
+
+        let note = Note::rand_in_range(&mut rng, 61, 61).unwrap();
+        assert_eq!(note.midi_num(), 61);
+        assert!(Note::rand_in_range(&mut rng, 61, 60).is_none());
+    }
+
+    #[test]
+    fn test_fretboard_note_at() {
+        let fb = standard_fretboard(0, 12);
+
+        let note = fb.note_at(&FretCoord { string: 6, fret: 5 }).unwrap();
+        assert_eq!(note.to_string(), "A/2");
+
+        let note = fb.note_at(&FretCoord { string: 2, fret: 2 }).unwrap();
+        assert_eq!(note.to_string(), "C#/4");
+
+        assert!(fb.note_at(&FretCoord { string: 7, fret: 0 }).is_none());
+        assert!(fb.note_at(&FretCoord { string: 0, fret: 0 }).is_none());
+        assert!(fb
+            .note_at(&FretCoord {
+                string: 1,
+                fret: 13
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn test_fretboard_capo() {
+        let fb = standard_fretboard(0, 4).with_capo(Some(Capo::full(2)));
+
+        assert_eq!(fb.fret_range(6), Some((2, 6)));
+        assert_eq!(fb.fret_span(), (2, 6));
+        assert_eq!(fb.open_note(6).unwrap().to_string(), "F#/2");
+        assert_eq!(fb.midi_range(), Some((42, 70)));
+        assert!(fb.note_at(&FretCoord { string: 6, fret: 1 }).is_none());
+        assert_eq!(
+            fb.note_at(&FretCoord { string: 1, fret: 6 })
+                .unwrap()
+                .to_string(),
+            "A#/4"
+        );
+        assert_eq!(fb.coords().count(), 6 * 5);
+
+        // the capo'd open E is F#, so E2 is gone from the board
+        assert!(fb.positions_of(note("E2")).is_empty());
+    }
+
+    #[test]
+    fn test_fretboard_partial_capo() {
+        // the "Esus" capo: strings 3 to 5 at the 2nd fret
+        let capo = Capo::partial(2, vec![3, 4, 5]);
+        let fb = standard_fretboard(0, 3).with_capo(Some(capo));
+
+        assert_eq!(fb.capo_fret(6), 0);
+        assert_eq!(fb.capo_fret(4), 2);
+        assert_eq!(fb.fret_range(1), Some((0, 3)));
+        assert_eq!(fb.fret_range(5), Some((2, 5)));
+        assert_eq!(fb.fret_range(7), None);
+        assert_eq!(fb.fret_span(), (0, 5));
+
+        let open: Vec<String> = (1..=6).map(|s| fb.open_note(s).unwrap().name()).collect();
+        assert_eq!(open, vec!["E", "B", "A", "E", "B", "E"]);
+
+        assert_eq!(
+            fb.positions_of(note("B2")),
+            vec![FretCoord { string: 5, fret: 2 }]
+        );
+        assert_eq!(fb.midi_range(), Some((40, 67)));
+    }
+
+    #[test]
+    fn test_fretboard_banjo() {
+        let preset = TuningPreset::Banjo;
+        let fb = Fretboard::new(preset.tuning(), 0, 7).with_strings(preset.string_specs());
+
+        // the 5th string starts at the 5th fret
+        let fifth = |fret| fb.note_at(&FretCoord { string: 5, fret });
+        assert_eq!(fifth(0).unwrap().to_string(), "G/4");
+        assert!(fifth(3).is_none());
+        assert!(fifth(5).is_none());
+        assert_eq!(fifth(7).unwrap().to_string(), "A/4");
+        assert_eq!(fb.coords().filter(|c| c.string == 5).count(), 3);
+
+        assert_eq!(
+            fb.positions_of(note("A4")),
+            vec![
+                FretCoord { string: 1, fret: 7 },
+                FretCoord { string: 5, fret: 7 }
+            ]
+        );
+
+        // a capo at 2 doesn't reach the 5th string, one at 7 does
+        let capoed = fb.clone().with_capo(Some(Capo::full(2)));
+        assert_eq!(capoed.open_note(5).unwrap().to_string(), "G/4");
+        assert_eq!(capoed.open_note(4).unwrap().to_string(), "E/3");
+        let capoed = fb.with_capo(Some(Capo::full(7)));
+        assert_eq!(capoed.open_note(5).unwrap().to_string(), "A/4");
+    }
+
+    #[test]
+    fn test_fretboard_string_specs() {
+        // only the low three strings, between frets 5 and 9
+        let low_strings = StringSpec {
+            start_fret: Some(5),
+            end_fret: Some(9),
+            ..StringSpec::default()
+        };
+        let skipped = StringSpec {
+            skipped: true,
+            ..StringSpec::default()
+        };
+        let mut specs = vec![skipped; 3];
+        specs.extend(vec![low_strings; 3]);
+        let fb = standard_fretboard(0, 12).with_strings(specs);
+
+        assert_eq!(fb.coords().count(), 3 * 5);
+        assert_eq!(fb.fret_range(1), None);
+        assert_eq!(fb.fret_span(), (5, 9));
+        assert_eq!(fb.midi_range(), Some((45, 59)));
+        assert!(fb.note_at(&FretCoord { string: 1, fret: 7 }).is_none());
+        // the 3rd fret of the A string is outside its window
+        assert_eq!(
+            fb.positions_of(note("C3")),
+            vec![FretCoord { string: 6, fret: 8 }]
+        );
+
+        // a drone string and a string missing the frets of a dulcimer
+        let mut specs = vec![StringSpec::default(); 6];
+        specs[0].drone = true;
+        specs[1].missing_frets = vec![1, 6, 8];
+        let fb = standard_fretboard(0, 8).with_strings(specs);
+
+        let frets = |string| -> Vec<i32> {
+            fb.coords()
+                .filter(|c| c.string == string)
+                .map(|c| c.fret)
+                .collect()
+        };
+        assert_eq!(frets(1), vec![0]);
+        assert_eq!(frets(2), vec![0, 2, 3, 4, 5, 7]);
+        assert_eq!(frets(3).len(), 9);
+    }
+
+    #[test]
+    fn test_fretboard_positions_of() {
+        let d_flat_4 = Note {
+            white_key: WhiteKey::D,
+            accidental: Some(Accidental::Flat),
+            octave: 4,
+        };
+
+        let fb = standard_fretboard(0, 12);
+        assert_eq!(
+            fb.positions_of(d_flat_4),
+            vec![
+                FretCoord { string: 2, fret: 2 },
+                FretCoord { string: 3, fret: 6 },
+                FretCoord {
+                    string: 4,
+                    fret: 11
+                },
+            ]
+        );
+
+        let fb = standard_fretboard(5, 9);
+        assert_eq!(
+            fb.positions_of(d_flat_4),
+            vec![FretCoord { string: 3, fret: 6 }]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use crate::theory::interval::Interval;
+use crate::theory::{FretCoord, Fretboard, Note};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScaleKind {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    MelodicMinor,
+    Ionian,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Aeolian,
+    Locrian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+}
+
+impl ScaleKind {
+    pub const ALL: [ScaleKind; 14] = [
+        Self::Major,
+        Self::NaturalMinor,
+        Self::HarmonicMinor,
+        Self::MelodicMinor,
+        Self::Ionian,
+        Self::Dorian,
+        Self::Phrygian,
+        Self::Lydian,
+        Self::Mixolydian,
+        Self::Aeolian,
+        Self::Locrian,
+        Self::MajorPentatonic,
+        Self::MinorPentatonic,
+        Self::Blues,
+    ];
+
+    fn interval_names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Major | Self::Ionian => &["P1", "M2", "M3", "P4", "P5", "M6", "M7"],
+            Self::NaturalMinor | Self::Aeolian => &["P1", "M2", "m3", "P4", "P5", "m6", "m7"],
+            Self::HarmonicMinor => &["P1", "M2", "m3", "P4", "P5", "m6", "M7"],
+            // ascending form
+            Self::MelodicMinor => &["P1", "M2", "m3", "P4", "P5", "M6", "M7"],
+            Self::Dorian => &["P1", "M2", "m3", "P4", "P5", "M6", "m7"],
+            Self::Phrygian => &["P1", "m2", "m3", "P4", "P5", "m6", "m7"],
+            Self::Lydian => &["P1", "M2", "M3", "A4", "P5", "M6", "M7"],
+            Self::Mixolydian => &["P1", "M2", "M3", "P4", "P5", "M6", "m7"],
+            Self::Locrian => &["P1", "m2", "m3", "P4", "d5", "m6", "m7"],
+            Self::MajorPentatonic => &["P1", "M2", "M3", "P5", "M6"],
+            Self::MinorPentatonic => &["P1", "m3", "P4", "P5", "m7"],
+            Self::Blues => &["P1", "m3", "P4", "d5", "P5", "m7"],
+        }
+    }
+
+    /// Intervals of each scale degree above the tonic.
+    pub fn intervals(&self) -> Vec<Interval> {
+        self.interval_names()
+            .iter()
+            .map(|name| name.parse().expect("scale intervals are valid"))
+            .collect()
+    }
+}
+
+impl fmt::Display for ScaleKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Major => "Major",
+                Self::NaturalMinor => "Natural Minor",
+                Self::HarmonicMinor => "Harmonic Minor",
+                Self::MelodicMinor => "Melodic Minor",
+                Self::Ionian => "Ionian",
+                Self::Dorian => "Dorian",
+                Self::Phrygian => "Phrygian",
+                Self::Lydian => "Lydian",
+                Self::Mixolydian => "Mixolydian",
+                Self::Aeolian => "Aeolian",
+                Self::Locrian => "Locrian",
+                Self::MajorPentatonic => "Major Pentatonic",
+                Self::MinorPentatonic => "Minor Pentatonic",
+                Self::Blues => "Blues",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Scale {
+    pub tonic: Note,
+    pub kind: ScaleKind,
+}
+
+impl Scale {
+    pub fn new(tonic: Note, kind: ScaleKind) -> Scale {
+        Scale { tonic, kind }
+    }
+
+    /// The scale's notes for one octave up from the tonic. Degrees that would
+    /// need more than a double accidental (only possible with a double sharp or
+    /// flat tonic) are left out.
+    pub fn notes(&self) -> Vec<Note> {
+        self.kind
+            .intervals()
+            .into_iter()
+            .filter_map(|interval| self.tonic.transpose(interval))
+            .collect()
+    }
+
+    /// Every coord on `fretboard` that plays a note of the scale, along with the
+    /// note spelled the way the scale spells it.
+    pub fn positions_on(&self, fretboard: &Fretboard) -> Vec<(FretCoord, Note)> {
+        let notes = self.notes();
+
+        fretboard
+            .coords()
+            .filter_map(|coord| {
+                let midi = fretboard.note_at(&coord)?.midi_num();
+                let degree = notes
+                    .iter()
+                    .find(|n| (midi - n.midi_num()).rem_euclid(12) == 0)?;
+                let note = Note {
+                    octave: degree.octave + (midi - degree.midi_num()) / 12,
+                    ..*degree
+                };
+
+                Some((coord, note))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.tonic.name(), self.kind)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::scale::*;
+    use crate::theory::TuningPreset;
+    use std::str::FromStr;
+
+    fn note_names(scale: Scale) -> Vec<String> {
+        scale.notes().iter().map(|n| n.name()).collect()
+    }
+
+    fn scale(tonic: &str, kind: ScaleKind) -> Scale {
+        Scale::new(Note::from_str(tonic).unwrap(), kind)
+    }
+
+    #[test]
+    fn test_scale_spelling() {
+        let cases = [
+            ("C4", ScaleKind::Major, "C D E F G A B"),
+            ("F#4", ScaleKind::Major, "F# G# A# B C# D# E#"),
+            ("Eb4", ScaleKind::NaturalMinor, "Eb F Gb Ab Bb Cb Db"),
+            ("A3", ScaleKind::HarmonicMinor, "A B C D E F G#"),
+            ("C4", ScaleKind::MelodicMinor, "C D Eb F G A B"),
+            ("A3", ScaleKind::Dorian, "A B C D E F# G"),
+            ("E3", ScaleKind::Phrygian, "E F G A B C D"),
+            ("F3", ScaleKind::Lydian, "F G A B C D E"),
+            ("G3", ScaleKind::Mixolydian, "G A B C D E F"),
+            ("B3", ScaleKind::Locrian, "B C D E F G A"),
+            ("G3", ScaleKind::MajorPentatonic, "G A B D E"),
+            ("E3", ScaleKind::MinorPentatonic, "E G A B D"),
+            ("A3", ScaleKind::Blues, "A C D Eb E G"),
+        ];
+
+        for (tonic, kind, expected) in cases {
+            assert_eq!(note_names(scale(tonic, kind)).join(" "), expected);
+        }
+
+        for kind in ScaleKind::ALL {
+            let notes = scale("D4", kind).notes();
+            assert_eq!(notes.len(), kind.intervals().len());
+            assert!(notes.windows(2).all(|w| w[0].midi_num() < w[1].midi_num()));
+        }
+    }
+
+    #[test]
+    fn test_scale_positions_on_fretboard() {
+        let fb = Fretboard::new(TuningPreset::Standard.tuning(), 5, 8);
+        let a_dorian = scale("A3", ScaleKind::Dorian);
+        let positions = a_dorian.positions_on(&fb);
+
+        let on_low_e: Vec<(i32, String)> = positions
+            .iter()
+            .filter(|(coord, _)| coord.string == 6)
+            .map(|(coord, note)| (coord.fret, note.to_string()))
+            .collect();
+        assert_eq!(
+            on_low_e,
+            vec![
+                (5, String::from("A/2")),
+                (7, String::from("B/2")),
+                (8, String::from("C/3"))
+            ]
+        );
+
+        for (coord, note) in &positions {
+            assert!(coord.fret >= 5 && coord.fret <= 8);
+            assert_eq!(fb.note_at(coord).unwrap().midi_num(), note.midi_num());
+        }
+        assert_eq!(positions.len(), 15);
+    }
+}
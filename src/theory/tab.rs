@@ -0,0 +1,287 @@
+use crate::theory::{FretCoord, Tuning};
+use std::{error, fmt};
+
+/// Dashes before each column, and after the last one.
+const COLUMN_GAP: &str = "--";
+
+/// Renders guitar tab, one line per string with string 1 on top. Each column
+/// holds the coords played together: one for a single note, several for a
+/// chord. Coords on strings the tuning doesn't have are left out.
+///
+/// ```text
+/// e|--0---------|
+/// B|--1--3------|
+/// G|--0-----12--|
+/// ```
+pub fn render_tab(tuning: &Tuning, columns: &[Vec<FretCoord>]) -> String {
+    let labels = string_labels(tuning);
+    let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = labels
+        .iter()
+        .map(|label| format!("{:<width$}|", label, width = label_width))
+        .collect();
+
+    for column in columns {
+        let fret_on = |string: usize| {
+            column
+                .iter()
+                .find(|c| c.string == string as i32 + 1)
+                .map(|c| c.fret.to_string())
+        };
+        let width = (0..lines.len())
+            .filter_map(fret_on)
+            .map(|f| f.len())
+            .max()
+            .unwrap_or(1);
+
+        for (i, line) in lines.iter_mut().enumerate() {
+            let cell = fret_on(i).unwrap_or_default();
+            line.push_str(COLUMN_GAP);
+            line.push_str(&format!("{:-<width$}", cell, width = width));
+        }
+    }
+
+    lines
+        .iter()
+        .map(|line| format!("{}{}|\n", line, COLUMN_GAP))
+        .collect()
+}
+
+/// Labels strings by note name. As is usual for guitar, when the top string
+/// shares its name with another string it is written in lower case.
+fn string_labels(tuning: &Tuning) -> Vec<String> {
+    let names: Vec<String> = tuning.iter().map(|n| n.name()).collect();
+
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            if i == 0 && names[1..].contains(name) {
+                name.to_lowercase()
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseTabError {
+    /// No lines with a `|` were found.
+    NoStaff,
+    /// A block of tab has a different number of lines than the first block.
+    StringCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for ParseTabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoStaff => write!(f, "no tab lines found"),
+            Self::StringCount { expected, found } => {
+                write!(f, "expected {} strings, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseTabError {}
+
+/// Parses tab in the format `render_tab` writes, returning its columns. Each
+/// line is a string, top line first, and everything before the first `|` is
+/// taken as the string's label and skipped. Digits that line up vertically
+/// belong to the same column, so frets can be aligned either way. Bar lines,
+/// `x`s and technique markings like `h`, `p` or `/` are ignored. Longer tabs
+/// can be written as several blocks separated by blank lines.
+pub fn parse_tab(text: &str) -> Result<Vec<Vec<FretCoord>>, ParseTabError> {
+    let mut columns = vec![];
+    let mut num_strings = None;
+
+    for block in tab_blocks(text) {
+        match num_strings {
+            None => num_strings = Some(block.len()),
+            Some(expected) if expected != block.len() => {
+                return Err(ParseTabError::StringCount {
+                    expected,
+                    found: block.len(),
+                })
+            }
+            _ => {}
+        }
+
+        columns.extend(parse_block(&block));
+    }
+
+    match num_strings {
+        Some(_) => Ok(columns),
+        None => Err(ParseTabError::NoStaff),
+    }
+}
+
+/// Splits tab into blocks of consecutive staff lines, keeping only what comes
+/// after each line's first `|`.
+fn tab_blocks(text: &str) -> Vec<Vec<Vec<char>>> {
+    let mut blocks = vec![];
+    let mut block = vec![];
+
+    for line in text.lines() {
+        match line.split_once('|') {
+            Some((_, staff)) => block.push(staff.chars().collect()),
+            None if !block.is_empty() => blocks.push(std::mem::take(&mut block)),
+            None => {}
+        }
+    }
+
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+fn parse_block(lines: &[Vec<char>]) -> Vec<Vec<FretCoord>> {
+    let len = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+    let is_digit = |line: &Vec<char>, pos: usize| line.get(pos).is_some_and(char::is_ascii_digit);
+    let has_digit: Vec<bool> = (0..len)
+        .map(|pos| lines.iter().any(|line| is_digit(line, pos)))
+        .collect();
+
+    let mut columns = vec![];
+    let mut pos = 0;
+
+    while pos < len {
+        if !has_digit[pos] {
+            pos += 1;
+            continue;
+        }
+
+        let end = (pos..len).find(|&p| !has_digit[p]).unwrap_or(len);
+        let column = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let digits: String = (pos..end)
+                    .filter(|&p| is_digit(line, p))
+                    .map(|p| line[p])
+                    .collect();
+
+                digits.parse().ok().map(|fret| FretCoord {
+                    string: i as i32 + 1,
+                    fret,
+                })
+            })
+            .collect();
+
+        columns.push(column);
+        pos = end;
+    }
+
+    columns
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theory::tab::*;
+    use crate::theory::TuningPreset;
+
+    fn coord(string: i32, fret: i32) -> FretCoord {
+        FretCoord { string, fret }
+    }
+
+    #[test]
+    fn test_render_tab() {
+        let tuning = TuningPreset::Standard.tuning();
+        let columns = vec![
+            vec![
+                coord(1, 0),
+                coord(2, 1),
+                coord(3, 0),
+                coord(4, 2),
+                coord(5, 3),
+            ],
+            vec![coord(2, 3)],
+            vec![coord(3, 12)],
+        ];
+
+        let expected = "\
+e|--0---------|
+B|--1--3------|
+G|--0-----12--|
+D|--2---------|
+A|--3---------|
+E|------------|
+";
+        assert_eq!(render_tab(&tuning, &columns), expected);
+
+        let uke = render_tab(&TuningPreset::Ukulele.tuning(), &[vec![coord(4, 2)]]);
+        assert_eq!(uke, "A|-----|\nE|-----|\nC|-----|\nG|--2--|\n");
+    }
+
+    #[test]
+    fn test_parse_tab_round_trip() {
+        let tuning = TuningPreset::Standard.tuning();
+        let columns = vec![
+            vec![coord(6, 3), coord(5, 2), coord(1, 3)],
+            vec![coord(4, 10)],
+            vec![coord(2, 15), coord(3, 9)],
+            vec![coord(6, 0)],
+        ];
+
+        let sorted = |cols: Vec<Vec<FretCoord>>| -> Vec<Vec<FretCoord>> {
+            cols.into_iter()
+                .map(|mut c| {
+                    c.sort_by_key(|c| c.string);
+                    c
+                })
+                .collect()
+        };
+
+        let parsed = parse_tab(&render_tab(&tuning, &columns)).unwrap();
+        assert_eq!(parsed, sorted(columns));
+    }
+
+    #[test]
+    fn test_parse_hand_written_tab() {
+        let text = "\
+Intro riff
+
+e|-----------|---------------|
+B|-----------|---------------|
+G|-------2h4-|-------x-------|
+D|---2/5-----|-------4-------|
+A|-0---------|--5/7----------|
+E|-----------|-------------10|
+
+e|-----|
+B|--3--|
+G|--2--|
+D|--0--|
+A|-----|
+E|-----|
+";
+        let parsed = parse_tab(text).unwrap();
+        let expected = vec![
+            vec![coord(5, 0)],
+            vec![coord(4, 2)],
+            vec![coord(4, 5)],
+            vec![coord(3, 2)],
+            vec![coord(3, 4)],
+            vec![coord(5, 5)],
+            vec![coord(5, 7)],
+            vec![coord(4, 4)],
+            vec![coord(6, 10)],
+            vec![coord(2, 3), coord(3, 2), coord(4, 0)],
+        ];
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn test_parse_tab_errors() {
+        assert_eq!(parse_tab("no tab here"), Err(ParseTabError::NoStaff));
+        assert_eq!(
+            parse_tab("e|--0--|\nB|--1--|\n\ne|--0--|\n"),
+            Err(ParseTabError::StringCount {
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+}
@@ -1,14 +1,16 @@
 use crate::app_state::{AppState, Room};
+use crate::audio::{self, pitch::PitchTracker};
 use crate::auth::{self, Claims};
-use crate::game::GameId;
-use crate::user::User;
+use crate::game::{self, clock, score::Standing, Game, GameId};
+use crate::theory::{self, FretCoord, Note};
+use crate::user::{User, UserId};
 use askama_axum::{IntoResponse, Response};
 use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::http::StatusCode;
 use axum::TypedHeader;
 use futures_util::{SinkExt, StreamExt};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::{Pool, Sqlite};
 use std::borrow::Cow;
@@ -27,7 +29,10 @@ pub async fn upgrade_ws(
 ) -> Result<Response, StatusCode> {
     if let Some(user) = auth::decode_user_cookie(&cookies) {
         println!("{:?} at {addr} connected to ws", &user);
-        Ok(ws.on_upgrade(move |socket| ws_callback(socket, state, user)))
+        // longer audio frames close the connection, see `process_audio_frame`
+        Ok(ws
+            .max_message_size(MAX_FRAME_BYTES)
+            .on_upgrade(move |socket| ws_callback(socket, state, user)))
     } else {
         Err(StatusCode::UNAUTHORIZED)
     }
@@ -43,8 +48,7 @@ struct ConnectMessage {
 async fn ws_callback(mut socket: WebSocket, state: Arc<AppState>, user: User) {
     let (mut ws_tx, mut ws_rx) = socket.split();
 
-    let mut channel = String::new();
-    let mut room_tx = None::<Sender<String>>;
+    let mut joined = None::<(GameId, Room)>;
 
     while let Some(Ok(message)) = ws_rx.next().await {
         if let Message::Text(text) = message {
@@ -61,28 +65,27 @@ async fn ws_callback(mut socket: WebSocket, state: Arc<AppState>, user: User) {
                 }
             };
 
-            channel = msg.channel.clone();
-
-            // new block to drop our lock on state.rooms
-            {
-                let mut rooms = state.rooms.lock().unwrap();
-                let room = rooms.entry(msg.channel).or_insert_with(Room::new);
-                room_tx = Some(room.tx.clone());
-            }
+            // "1" and "01" are the same game, so the same room
+            let Ok(game_id) = msg.channel.parse::<GameId>() else {
+                tracing::error!("not a game channel: {:?}", &msg.channel);
+                let _ = ws_tx
+                    .send(Message::Text(String::from("Unknown channel")))
+                    .await;
+                continue;
+            };
 
-            if room_tx.is_some() {
-                break;
-            } else {
-                tracing::error!("somehow, room_tx is None :(");
-                return;
-            }
+            joined = Some((game_id, state.room(game_id)));
+            break;
         }
     }
 
-    let room_tx = room_tx.expect("room tx not present");
+    let Some((game_id, room)) = joined else {
+        return;
+    };
+    let room_tx = room.tx.clone();
     let mut room_rx = room_tx.subscribe();
 
-    let msg = format!("{} joined channel {}", &user.name, &channel);
+    let msg = format!("{} joined channel {}", &user.name, game_id);
     tracing::debug!("{}", msg);
     let _ = room_tx.send(msg);
 
@@ -95,12 +98,27 @@ async fn ws_callback(mut socket: WebSocket, state: Arc<AppState>, user: User) {
     });
 
     let mut recv_task = {
-        let tx = room_tx.clone();
         let name = user.name.clone();
+        let user_id = user.id;
 
         tokio::spawn(async move {
-            while let Some(Ok(Message::Text(text))) = ws_rx.next().await {
-                process_message(&state.pool, &tx, &text).await;
+            let conn = Conn {
+                pool: &state.pool,
+                room,
+                game_id,
+                user_id,
+            };
+            let mut pitch_tracker = None::<PitchTracker>;
+
+            while let Some(Ok(message)) = ws_rx.next().await {
+                match message {
+                    Message::Text(text) => process_message(&conn, &text).await,
+                    Message::Binary(bytes) => {
+                        process_audio_frame(&conn, &mut pitch_tracker, &bytes).await
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
             }
         })
     };
@@ -115,20 +133,55 @@ async fn ws_callback(mut socket: WebSocket, state: Arc<AppState>, user: User) {
     let _ = room_tx.send(msg);
 }
 
+/// The connection a message arrived on: who sent it, and where to report it.
+struct Conn<'a> {
+    pool: &'a Pool<Sqlite>,
+    /// The room of the channel's game, for reporting to everyone in it and
+    /// changing the game, see `game::db::change_game`.
+    room: Room,
+    game_id: GameId,
+    user_id: UserId,
+}
+
 #[derive(Debug, Deserialize)]
 enum AppMessage {
-    // JoinGame { token: String },
-    StartGame { token: String, game_id: GameId },
+    /// Takes a seat in the channel's game.
+    JoinGame,
+    LeaveGame,
+    StartGame {
+        token: String,
+        game_id: GameId,
+    },
+    /// A note-on from a MIDI instrument, with its channel counted from 1.
+    /// Guitar controllers in MPE mode keep channel 1 for the whole
+    /// instrument and send each string on its own channel after it, so for
+    /// them the channel also says which string was played, see `mpe_string`.
+    MidiGuess {
+        note: i32,
+        channel: Option<i32>,
+    },
+    /// A click on the game's fretboard, graded and recorded by the server.
+    FretGuess {
+        string: i32,
+        fret: i32,
+    },
 }
 
-async fn process_message(pool: &Pool<Sqlite>, tx: &Sender<String>, text: &str) {
+async fn process_message(conn: &Conn<'_>, text: &str) {
     match serde_json::from_str::<AppMessage>(text) {
         Ok(msg) => {
             tracing::debug!("msg processed: {:?}", &msg);
-            tx.send(format!("{:?}", msg));
+            conn.room.tx.send(format!("{:?}", msg));
             match msg {
-                // AppMessage::JoinGame { .. } => {}
-                AppMessage::StartGame { .. } => {}
+                AppMessage::JoinGame => handle_roster_change(conn, true).await,
+                AppMessage::LeaveGame => handle_roster_change(conn, false).await,
+                AppMessage::StartGame { game_id, .. } => handle_start_game(conn, game_id).await,
+                AppMessage::MidiGuess { note, channel } => {
+                    handle_midi_guess(conn, note, channel).await
+                }
+                AppMessage::FretGuess { string, fret } => {
+                    handle_fret_guess(conn, FretCoord { string, fret }).await
+                }
             }
         }
         Err(e) => {
@@ -137,9 +190,301 @@ async fn process_message(pool: &Pool<Sqlite>, tx: &Sender<String>, text: &str) {
     }
 }
 
-async fn handle_start_game(pool: &Pool<Sqlite>, token: String) {
-    if let Ok(claims) = auth::decode_user_token(token.as_str()) {
-        // if let Ok(mut game) = game::db::fetch_game(pool, )
+/// Sample rates accepted in binary audio frames.
+const MIN_SAMPLE_RATE: u32 = 8_000;
+const MAX_SAMPLE_RATE: u32 = 192_000;
+/// Faster audio is downsampled before pitch tracking, whose work grows with
+/// the square of the sample rate.
+const MAX_TRACKED_SAMPLE_RATE: u32 = 48_000;
+/// Most samples in one binary audio frame, a little over a second at 192 kHz.
+const MAX_FRAME_SAMPLES: usize = 1 << 18;
+/// The sample rate and the samples, see `audio::decode_pcm_frame`.
+const MAX_FRAME_BYTES: usize = 4 + 4 * MAX_FRAME_SAMPLES;
+
+#[derive(Debug, Serialize)]
+pub enum ServerMessage {
+    /// A graded note a player played. `cents` is only known for notes picked
+    /// out of audio, `string` only for notes from MIDI guitar controllers.
+    NotePlayed {
+        user_id: UserId,
+        note: String,
+        cents: Option<f64>,
+        string: Option<i32>,
+        is_correct: bool,
+    },
+    /// A graded fretboard click. `round_over` is set when it ended the round.
+    FretGuessed {
+        user_id: UserId,
+        string: i32,
+        fret: i32,
+        is_correct: bool,
+        round_over: bool,
+    },
+    /// A round closed, by guesses or by its time running out. `round` counts
+    /// from 1.
+    RoundOver {
+        round: usize,
+        standings: Vec<Standing>,
+    },
+    RoundStarted {
+        round: usize,
+    },
+    GameOver {
+        standings: Vec<Standing>,
+    },
+    /// Someone joined or left. `status` changes when the last player leaves.
+    RosterChanged {
+        player_ids: Vec<UserId>,
+        host_id: Option<UserId>,
+        status: String,
+    },
+}
+
+/// MPE manager channel, for messages about the whole instrument.
+const MPE_MANAGER_CHANNEL: i32 = 1;
+const MAX_MIDI_CHANNEL: i32 = 16;
+
+/// The string an MPE guitar controller played a note on, from the note's
+/// channel: string 1 on channel 2, string 2 on channel 3, and so on. Notes
+/// on the manager channel aren't on any one string.
+fn mpe_string(channel: i32) -> Option<i32> {
+    ((MPE_MANAGER_CHANNEL + 1)..=MAX_MIDI_CHANNEL)
+        .contains(&channel)
+        .then(|| channel - MPE_MANAGER_CHANNEL)
+}
+
+async fn handle_midi_guess(conn: &Conn<'_>, midi: i32, channel: Option<i32>) {
+    if !(0..=theory::MAX_MIDI).contains(&midi) {
+        tracing::error!("midi note out of range: {}", midi);
+        return;
+    }
+
+    let string = channel.and_then(mpe_string);
+    handle_note_guess(conn, Note::from_midi(midi), None, string).await;
+}
+
+/// Feeds a binary frame of audio (see `audio::decode_pcm_frame`) to the
+/// connection's pitch tracker and grades any notes it picks out, see
+/// `handle_note_guess`.
+async fn process_audio_frame(
+    conn: &Conn<'_>,
+    pitch_tracker: &mut Option<PitchTracker>,
+    bytes: &[u8],
+) {
+    if bytes.len() > MAX_FRAME_BYTES {
+        tracing::error!("audio frame too long: {} bytes", bytes.len());
+        return;
+    }
+    let Some((sample_rate, samples)) = audio::decode_pcm_frame(bytes) else {
+        tracing::error!("malformed audio frame of {} bytes", bytes.len());
+        return;
+    };
+
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+        tracing::error!("unsupported sample rate: {}", sample_rate);
+        return;
+    }
+
+    // tracking pitch is too slow to run on the async workers, so the
+    // tracker goes to a blocking thread and comes back with the notes
+    let tracker = pitch_tracker.take();
+    let tracked = tokio::task::spawn_blocking(move || {
+        let (sample_rate, samples) =
+            audio::downsample(sample_rate, samples, MAX_TRACKED_SAMPLE_RATE);
+        // a change of sample rate starts a new stream
+        let mut tracker = tracker
+            .filter(|tracker| tracker.sample_rate() == sample_rate)
+            .unwrap_or_else(|| PitchTracker::new(sample_rate, theory::A4_HZ));
+        let played = tracker.push(&samples);
+        (tracker, played)
+    })
+    .await;
+    let played = match tracked {
+        Ok((tracker, played)) => {
+            *pitch_tracker = Some(tracker);
+            played
+        }
+        Err(e) => {
+            tracing::error!("pitch tracking failed: {}", e);
+            return;
+        }
+    };
+
+    for (note, cents) in played {
+        handle_note_guess(conn, note, Some(cents), None).await;
+    }
+}
+
+async fn handle_fret_guess(conn: &Conn<'_>, coord: FretCoord) {
+    let changed = game::db::change_game(conn.pool, &conn.room.lock, conn.game_id, |game| {
+        game.submit_guess(conn.user_id, coord)
+    })
+    .await;
+    let (game, is_correct) = match changed {
+        Ok(changed) => changed,
+        Err(e) => {
+            tracing::debug!("guess rejected: {}", e);
+            return;
+        }
+    };
+    let round_over = game.status == game::Status::RoundOver;
+
+    send_server_message(
+        &conn.room.tx,
+        &ServerMessage::FretGuessed {
+            user_id: conn.user_id,
+            string: coord.string,
+            fret: coord.fret,
+            is_correct,
+            round_over,
+        },
+    );
+    if round_over {
+        send_round_over(conn, &game);
+    }
+}
+
+/// Reports a round a guess just ended.
+fn send_round_over(conn: &Conn<'_>, game: &Game) {
+    send_server_message(
+        &conn.room.tx,
+        &ServerMessage::RoundOver {
+            round: game.rounds.len(),
+            standings: game.standings(),
+        },
+    );
+    // the break before the next round starts now
+    conn.room.clock.notify_one();
+}
+
+/// Sends `msg` to everyone in the room as JSON.
+pub fn send_server_message(tx: &Sender<String>, msg: &ServerMessage) {
+    match serde_json::to_string(msg) {
+        Ok(json) => {
+            let _ = tx.send(json);
+        }
+        Err(e) => tracing::error!("error serializing msg: {:?}", e),
+    }
+}
+
+/// Grades and records a played note like a fretboard click, see
+/// `Game::submit_note_guess`, and reports it.
+async fn handle_note_guess(conn: &Conn<'_>, note: Note, cents: Option<f64>, string: Option<i32>) {
+    let changed = game::db::change_game(conn.pool, &conn.room.lock, conn.game_id, |game| {
+        game.submit_note_guess(conn.user_id, note, string)
+    })
+    .await;
+    let (game, is_correct) = match changed {
+        Ok(changed) => changed,
+        Err(e) => {
+            tracing::debug!("note rejected: {}", e);
+            return;
+        }
+    };
+
+    let msg = ServerMessage::NotePlayed {
+        user_id: conn.user_id,
+        note: note.to_string(),
+        cents,
+        string,
+        is_correct,
+    };
+    tracing::debug!("note played: {:?}", &msg);
+    send_server_message(&conn.room.tx, &msg);
+    if game.status == game::Status::RoundOver {
+        send_round_over(conn, &game);
+    }
+}
+
+/// Adds the sender to the channel's game or removes them from it.
+async fn handle_roster_change(conn: &Conn<'_>, joining: bool) {
+    let changed = game::db::change_game(conn.pool, &conn.room.lock, conn.game_id, |game| {
+        let was_playing = game.status == game::Status::Playing;
+        if joining {
+            game.join(conn.user_id)?;
+        } else {
+            game.leave(conn.user_id)?;
+        }
+        Ok(was_playing)
+    })
+    .await;
+    let (game, was_playing) = match changed {
+        Ok(changed) => changed,
+        Err(e) => {
+            tracing::debug!(
+                "user {} can't join or leave game {}: {}",
+                conn.user_id,
+                conn.game_id,
+                e
+            );
+            return;
+        }
+    };
+
+    send_server_message(
+        &conn.room.tx,
+        &ServerMessage::RosterChanged {
+            player_ids: game.player_ids.clone(),
+            host_id: game.host_id,
+            status: game.status.to_string(),
+        },
+    );
+    // leaving can end the round
+    if was_playing && game.status == game::Status::RoundOver {
+        send_server_message(
+            &conn.room.tx,
+            &ServerMessage::RoundOver {
+                round: game.rounds.len(),
+                standings: game.standings(),
+            },
+        );
+    }
+    conn.room.clock.notify_one();
+}
+
+/// Starts the game if the sender is its host, and hands it to a clock.
+async fn handle_start_game(conn: &Conn<'_>, game_id: GameId) {
+    if conn.game_id != game_id {
+        tracing::error!("can't start game {} from another channel", game_id);
+        return;
+    }
+    let started = game::db::change_game(conn.pool, &conn.room.lock, game_id, |game| {
+        if game.host_id != Some(conn.user_id) {
+            return Ok(false);
+        }
+        game.start()?;
+        Ok(true)
+    })
+    .await;
+    match started {
+        Ok((_, true)) => {}
+        Ok((_, false)) => {
+            tracing::debug!("user {} isn't the host of game {}", conn.user_id, game_id);
+            return;
+        }
+        Err(e) => {
+            tracing::debug!("can't start game {}: {}", game_id, e);
+            return;
+        }
+    }
+
+    tracing::debug!("game started: {}", game_id);
+    send_server_message(&conn.room.tx, &ServerMessage::RoundStarted { round: 1 });
+    clock::start(conn.pool, conn.room.clone(), game_id);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ws::*;
+
+    #[test]
+    fn test_mpe_string() {
+        assert_eq!(mpe_string(1), None);
+        assert_eq!(mpe_string(2), Some(1));
+        assert_eq!(mpe_string(7), Some(6));
+        assert_eq!(mpe_string(16), Some(15));
+        assert_eq!(mpe_string(0), None);
+        assert_eq!(mpe_string(17), None);
     }
 }
 